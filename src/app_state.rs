@@ -0,0 +1,124 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use std::path::PathBuf;
+
+use crate::{config::LOG_DOMAIN, util};
+
+const GROUP: &str = "FileSelector";
+const KEY_LAST_FOLDER: &str = "last-folder";
+const KEY_LAST_FILTER: &str = "last-filter";
+const KEY_RECENT_FOLDERS: &str = "recent-folders";
+
+// How many recently visited folders to remember
+const MAX_RECENT_FOLDERS: usize = 10;
+
+// What we remember about an application's last use of the file selector
+#[derive(Debug, Default)]
+pub struct AppState {
+    app_id: String,
+    // URI of the folder the last selection was made in
+    pub last_folder: Option<String>,
+    // Name of the filter used for the last selection
+    pub last_filter: Option<String>,
+    // URIs of recently visited folders, most recent last
+    pub recent_folders: Vec<String>,
+}
+
+impl AppState {
+    pub fn load(app_id: &str) -> Self {
+        let mut state = AppState {
+            app_id: app_id.to_string(),
+            ..Default::default()
+        };
+
+        let Some(path) = state.path() else {
+            return state;
+        };
+
+        let keyfile = glib::KeyFile::new();
+        if let Err(err) = keyfile.load_from_file(&path, glib::KeyFileFlags::NONE) {
+            if !err.matches(glib::FileError::Noent) {
+                glib::g_warning!(LOG_DOMAIN, "Failed to load state {path:#?}: {err}");
+            }
+            return state;
+        }
+
+        state.last_folder = keyfile
+            .string(GROUP, KEY_LAST_FOLDER)
+            .ok()
+            .map(|uri| uri.to_string());
+        state.last_filter = keyfile
+            .string(GROUP, KEY_LAST_FILTER)
+            .ok()
+            .map(|name| name.to_string());
+        state.recent_folders = keyfile
+            .string_list(GROUP, KEY_RECENT_FOLDERS)
+            .map(|uris| uris.iter().map(|uri| uri.to_string()).collect())
+            .unwrap_or_default();
+
+        state
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let Some(path) = self.path() else {
+            return Ok(());
+        };
+
+        let keyfile = glib::KeyFile::new();
+        if let Some(uri) = &self.last_folder {
+            keyfile.set_string(GROUP, KEY_LAST_FOLDER, uri);
+        }
+        if let Some(name) = &self.last_filter {
+            keyfile.set_string(GROUP, KEY_LAST_FILTER, name);
+        }
+        let recent = self
+            .recent_folders
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        keyfile.set_string_list(GROUP, KEY_RECENT_FOLDERS, &recent);
+
+        if let Some(dir) = path.parent() {
+            if let Err(err) =
+                gio::File::for_path(dir).make_directory_with_parents(None::<&gio::Cancellable>)
+            {
+                if !err.matches(gio::IOErrorEnum::Exists) {
+                    return Err(err);
+                }
+            }
+        }
+
+        keyfile.save_to_file(&path)
+    }
+
+    pub fn add_recent_folder(&mut self, uri: &str) {
+        self.recent_folders.retain(|recent| recent != uri);
+        self.recent_folders.push(uri.to_string());
+
+        let excess = self.recent_folders.len().saturating_sub(MAX_RECENT_FOLDERS);
+        self.recent_folders.drain(..excess);
+    }
+
+    // Without a usable app id there's nothing to key the state by
+    fn path(&self) -> Option<PathBuf> {
+        let name = format!("{}.ini", self.app_id);
+        if self.app_id.is_empty() || self.app_id.starts_with('.') {
+            return None;
+        }
+        util::validate_filename(&name).ok()?;
+
+        Some(
+            glib::user_state_dir()
+                .join("phosh-file-selector")
+                .join(name),
+        )
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use gtk::prelude::*;
+use gtk::{gio, glib};
+
+use crate::util;
+
+// A bookmark as stored in GTK's bookmarks file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub uri: String,
+    pub label: Option<String>,
+}
+
+impl Bookmark {
+    pub fn new(uri: &str) -> Self {
+        Bookmark {
+            uri: uri.to_string(),
+            label: None,
+        }
+    }
+
+    pub fn file(&self) -> gio::File {
+        gio::File::for_uri(&self.uri)
+    }
+
+    // The label if set, otherwise a name derived from the URI
+    pub fn name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+
+        util::folder_to_name(self.file())
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        let file = self.file();
+
+        if file.is_native() {
+            util::folder_to_icon_name(file)
+        } else {
+            "folder-remote-symbolic"
+        }
+    }
+}
+
+pub fn bookmarks_file() -> gio::File {
+    let path = glib::user_config_dir().join("gtk-3.0").join("bookmarks");
+    gio::File::for_path(path)
+}
+
+// Each line holds a URI optionally followed by a space and a label
+pub fn parse(contents: &str) -> Vec<Bookmark> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.split_once(' ') {
+            Some((uri, label)) if !label.is_empty() => Bookmark {
+                uri: uri.to_string(),
+                label: Some(label.to_string()),
+            },
+            Some((uri, _)) => Bookmark::new(uri),
+            None => Bookmark::new(line),
+        })
+        .collect()
+}
+
+pub fn serialize(bookmarks: &[Bookmark]) -> String {
+    bookmarks
+        .iter()
+        .map(|bookmark| match &bookmark.label {
+            Some(label) => format!("{} {label}\n", bookmark.uri),
+            None => format!("{}\n", bookmark.uri),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bookmarks() {
+        let bookmarks = parse(
+            "file:///home/user/My%20Documents\n\
+             sftp://host/srv/data Project Data\n\
+             \n\
+             file:///tmp \n",
+        );
+
+        assert_eq!(
+            bookmarks,
+            vec![
+                Bookmark::new("file:///home/user/My%20Documents"),
+                Bookmark {
+                    uri: "sftp://host/srv/data".to_string(),
+                    label: Some("Project Data".to_string()),
+                },
+                Bookmark::new("file:///tmp"),
+            ]
+        );
+    }
+
+    #[test]
+    fn roundtrip_bookmarks() {
+        let bookmarks = vec![
+            Bookmark::new("file:///home/user/%C3%BCber%20uns"),
+            Bookmark {
+                uri: "smb://server/share%20name".to_string(),
+                label: Some("Shared stuff with spaces".to_string()),
+            },
+            Bookmark {
+                uri: "dav://host/path".to_string(),
+                label: Some("Ünïcödé".to_string()),
+            },
+        ];
+
+        let contents = serialize(&bookmarks);
+        assert_eq!(
+            contents,
+            "file:///home/user/%C3%BCber%20uns\n\
+             smb://server/share%20name Shared stuff with spaces\n\
+             dav://host/path Ünïcödé\n"
+        );
+        assert_eq!(parse(&contents), bookmarks);
+    }
+}
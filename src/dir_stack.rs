@@ -9,12 +9,19 @@
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::Signal;
-use glib_macros::Properties;
-use gtk::{gio, glib, CompositeTemplate};
+use glib_macros::{clone, Properties};
+use gtk::{gdk, gio, glib, CompositeTemplate};
 use std::cell::{Cell, RefCell};
 use std::sync::OnceLock;
 
-use crate::config::LOG_DOMAIN;
+use crate::{config::LOG_DOMAIN, util};
+
+// Extra mouse buttons as used for back and forward in browsers
+const BUTTON_BACK: u32 = 8;
+const BUTTON_FORWARD: u32 = 9;
+
+// Minimum horizontal velocity (in pixels per second) for swipes to navigate
+const SWIPE_MIN_VELOCITY: f64 = 500.0;
 
 mod imp {
     use super::*;
@@ -36,6 +43,9 @@ mod imp {
         pub(super) is_updating: Cell<bool>,
         pub(super) position: Cell<usize>,
         pub(super) dirstack: RefCell<Vec<gio::File>>,
+        pub(super) history_popover: RefCell<Option<gtk::Popover>>,
+        pub(super) volume_monitor: RefCell<Option<gio::VolumeMonitor>>,
+        pub(super) check_cancellable: RefCell<Option<gio::Cancellable>>,
     }
 
     #[glib::object_subclass]
@@ -56,6 +66,13 @@ mod imp {
             klass.install_action("dir.prev", None, move |dirstack, _, _| {
                 dirstack.goto(-1);
             });
+            klass.install_action("dir.up", None, move |dirstack, _, _| {
+                dirstack.up();
+            });
+            klass.install_action("dir.home", None, move |dirstack, _, _| {
+                let home = gio::File::for_path(glib::home_dir());
+                dirstack.emit_by_name::<()>("new-uri", &[&home.uri()]);
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -72,12 +89,111 @@ mod imp {
             self.obj().action_set_enabled("dir.prev", enabled);
         }
 
+        // Going up only makes sense for real folders that aren't the root
+        fn update_up_action(&self) {
+            let enabled = self
+                .folder
+                .borrow()
+                .as_ref()
+                .is_some_and(|folder| folder.path().is_some() && folder.parent().is_some());
+            self.obj().action_set_enabled("dir.up", enabled);
+        }
+
+        // Drop entries for which `gone` returns true. The current entry is
+        // kept as that's what's displayed.
+        pub(super) fn prune(&self, gone: impl Fn(&gio::File) -> bool) {
+            let mut stack = self.dirstack.borrow_mut();
+            let old = std::mem::take(&mut *stack);
+            let mut pos = None;
+
+            for (index, folder) in old.into_iter().enumerate() {
+                let is_current = index == self.position.get();
+
+                if !is_current && gone(&folder) {
+                    glib::g_debug!(LOG_DOMAIN, "Pruning {:#?}", folder.uri());
+                    continue;
+                }
+
+                // Pruning can leave the same folder twice in a row
+                if stack.last().is_some_and(|last| last.equal(&folder)) {
+                    if is_current {
+                        pos = Some(stack.len() - 1);
+                    }
+                    continue;
+                }
+
+                if is_current {
+                    pos = Some(stack.len());
+                }
+                stack.push(folder);
+            }
+
+            let pos = pos.unwrap_or(stack.len().saturating_sub(1));
+            self.position.replace(pos);
+            self.update_actions(pos, stack.len());
+        }
+
+        // Look for deleted folders in the background and drop them from the
+        // history. Only local folders are checked, remote ones could need a
+        // network round trip each.
+        pub(super) fn check_history(&self) {
+            if let Some(cancellable) = self.check_cancellable.take() {
+                cancellable.cancel();
+            }
+            let cancellable = gio::Cancellable::new();
+            self.check_cancellable.replace(Some(cancellable.clone()));
+
+            let folders = self
+                .dirstack
+                .borrow()
+                .iter()
+                .filter(|folder| folder.is_native())
+                .cloned()
+                .collect::<Vec<_>>();
+
+            glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let mut missing = Vec::new();
+
+                    for folder in folders {
+                        let info = gio::CancellableFuture::new(
+                            folder.query_info_future(
+                                gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+                                gio::FileQueryInfoFlags::NONE,
+                                glib::Priority::LOW,
+                            ),
+                            cancellable.clone(),
+                        )
+                        .await;
+
+                        match info {
+                            // A newer check is underway
+                            Err(_) => return,
+                            Ok(Err(err)) if err.matches(gio::IOErrorEnum::NotFound) => {
+                                missing.push(folder)
+                            }
+                            Ok(_) => {}
+                        }
+                    }
+
+                    if !missing.is_empty() {
+                        this.prune(|folder| missing.iter().any(|gone| gone.equal(folder)));
+                    }
+                }
+            ));
+        }
+
         fn set_folder(&self, folder: Option<gio::File>) {
             let Some(folder) = folder else { return };
 
-            // Update triggered by us, ignore
+            // Update triggered by us, don't stack it
             if self.is_updating.get() {
                 self.is_updating.replace(false);
+                *self.folder.borrow_mut() = Some(folder);
+                self.update_up_action();
+                self.check_history();
                 return;
             }
 
@@ -94,6 +210,9 @@ mod imp {
 
             *self.folder.borrow_mut() = Some(folder);
             self.update_actions(pos, stack.len());
+            drop(stack);
+            self.update_up_action();
+            self.check_history();
         }
     }
 
@@ -101,6 +220,50 @@ mod imp {
     impl ObjectImpl for DirStack {
         fn constructed(&self) {
             self.parent_constructed();
+
+            // Handle the shortcuts regardless of the focused widget
+            let controller = gtk::ShortcutController::new();
+            controller.set_scope(gtk::ShortcutScope::Global);
+            for (trigger, action) in [
+                ("<Alt>Left", "dir.prev"),
+                ("<Alt>Right", "dir.next"),
+                ("<Alt>Up", "dir.up"),
+                ("BackSpace", "dir.up"),
+                ("<Alt>Home", "dir.home"),
+            ] {
+                controller.add_shortcut(gtk::Shortcut::new(
+                    gtk::ShortcutTrigger::parse_string(trigger),
+                    Some(gtk::NamedAction::new(action)),
+                ));
+            }
+            self.obj().add_controller(controller);
+
+            self.obj().add_history_popover(&self.prev_btn.get(), -1);
+            self.obj().add_history_popover(&self.next_btn.get(), 1);
+
+            // Entries on unmounted file systems can't be visited anymore
+            let monitor = gio::VolumeMonitor::get();
+            monitor.connect_mount_removed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_, mount| {
+                    if let Some(popover) = this.history_popover.borrow().as_ref() {
+                        popover.popdown();
+                    }
+                    let root = mount.root();
+                    this.prune(|folder| root.equal(folder) || folder.has_prefix(&root));
+                }
+            ));
+            *self.volume_monitor.borrow_mut() = Some(monitor);
+
+            self.update_actions(0, 0);
+            self.update_up_action();
+        }
+
+        fn dispose(&self) {
+            if let Some(popover) = self.history_popover.take() {
+                popover.unparent();
+            }
         }
 
         fn signals() -> &'static [Signal] {
@@ -134,27 +297,207 @@ impl DirStack {
         Self::default()
     }
 
+    // Move `skip` entries through the history. Jumps past either end are
+    // clamped to the first or last entry.
     pub fn goto(&self, skip: i64) {
-        let mut pos = self.imp().position.get() as i64;
-        let stack = self.imp().dirstack.borrow_mut();
+        if skip == 0 {
+            return;
+        }
+
+        let stack = self.imp().dirstack.borrow();
         let len = stack.len() as i64;
+        let pos = self.imp().position.get() as i64;
 
-        if skip == 0 {
+        if len == 0 {
             return;
         }
 
-        if (skip < 0 && pos + skip >= 0) || (skip > 0 && skip + pos < len) {
-            pos += skip;
-        } else {
-            panic!("Cannot skip {skip:#?} at {pos:#?} with {len:#?}");
+        let target = (pos + skip).clamp(0, len - 1);
+        if target != pos + skip {
+            glib::g_debug!(
+                LOG_DOMAIN,
+                "Cannot skip {skip:#?} at {pos:#?} with {len:#?}, going to {target:#?}"
+            );
         }
 
-        let pos = pos as usize;
+        if target == pos {
+            return;
+        }
+
+        let pos = target as usize;
         self.imp().position.replace(pos);
         let uri = stack[pos].uri();
+        drop(stack);
         self.imp().update_actions(pos, len as usize);
 
         self.imp().is_updating.replace(true);
-        self.imp().obj().emit_by_name::<()>("new-uri", &[&uri]);
+        self.emit_by_name::<()>("new-uri", &[&uri]);
+    }
+
+    // Put `folders` (oldest first) in front of the current folder so they
+    // can be reached by going back
+    pub fn set_history(&self, folders: &[gio::File]) {
+        let imp = self.imp();
+        let mut stack = imp.dirstack.borrow_mut();
+        let current = stack.get(imp.position.get()).cloned();
+
+        let mut history = folders
+            .iter()
+            .filter(|folder| {
+                !current
+                    .as_ref()
+                    .is_some_and(|current| current.equal(*folder))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        history.extend(current);
+
+        glib::g_debug!(LOG_DOMAIN, "Restored {} history entries", history.len());
+        let pos = history.len().saturating_sub(1);
+        *stack = history;
+        imp.position.replace(pos);
+        imp.update_actions(pos, stack.len());
+        drop(stack);
+        imp.check_history();
+    }
+
+    fn up(&self) {
+        let Some(parent) = self.folder().and_then(|folder| folder.parent()) else {
+            return;
+        };
+
+        let uri = parent.uri();
+        glib::g_debug!(LOG_DOMAIN, "Going up to {uri:#?}");
+        self.emit_by_name::<()>("new-uri", &[&uri]);
+    }
+
+    // Long press or right click on `button` lists the history in `direction`
+    fn add_history_popover(&self, button: &gtk::Button, direction: i64) {
+        let click = gtk::GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        click.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            button,
+            move |gesture, _, _, _| {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                this.show_history(&button, direction);
+            }
+        ));
+        button.add_controller(click);
+
+        let long_press = gtk::GestureLongPress::new();
+        long_press.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            button,
+            move |gesture, _, _| {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                this.show_history(&button, direction);
+            }
+        ));
+        button.add_controller(long_press);
+    }
+
+    fn show_history(&self, button: &gtk::Button, direction: i64) {
+        let pos = self.imp().position.get() as i64;
+        let len = self.imp().dirstack.borrow().len() as i64;
+
+        // Nearest entries first
+        let targets = if direction < 0 {
+            (0..pos).rev().collect::<Vec<_>>()
+        } else {
+            (pos + 1..len).collect::<Vec<_>>()
+        };
+        if targets.is_empty() {
+            return;
+        }
+
+        let list = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let popover = gtk::Popover::builder()
+            .child(&list)
+            .css_classes(["menu"])
+            .build();
+
+        for target in targets {
+            let folder = self.imp().dirstack.borrow()[target as usize].clone();
+
+            let content = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            content.append(&gtk::Image::from_icon_name(util::folder_to_icon_name(
+                folder.clone(),
+            )));
+            content.append(&gtk::Label::new(Some(&util::folder_to_name(folder))));
+
+            let row = gtk::Button::builder()
+                .child(&content)
+                .css_classes(["flat"])
+                .build();
+            row.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                popover,
+                move |_| {
+                    popover.popdown();
+                    let pos = this.imp().position.get() as i64;
+                    this.goto(target - pos);
+                }
+            ));
+            list.append(&row);
+        }
+
+        if let Some(old) = self.imp().history_popover.replace(Some(popover.clone())) {
+            old.unparent();
+        }
+        popover.set_parent(button);
+        popover.popup();
+    }
+
+    // Mouse buttons on `widget` navigate the history
+    pub fn add_history_gestures(&self, widget: &impl IsA<gtk::Widget>) {
+        let click = gtk::GestureClick::builder().button(0).build();
+        click.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |gesture, _, _, _| {
+                let action = match gesture.current_button() {
+                    BUTTON_BACK => "dir.prev",
+                    BUTTON_FORWARD => "dir.next",
+                    _ => return,
+                };
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                let _ = this.activate_action(action, None);
+            }
+        ));
+        widget.add_controller(click);
+    }
+
+    // Horizontal swipes on `widget` navigate the history. Only use this on
+    // widgets that don't scroll horizontally themselves.
+    pub fn add_history_swipe(&self, widget: &impl IsA<gtk::Widget>) {
+        let swipe = gtk::GestureSwipe::new();
+        swipe.connect_swipe(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, velocity_x, velocity_y| {
+                // Only act on clearly horizontal swipes
+                if velocity_x.abs() < SWIPE_MIN_VELOCITY
+                    || velocity_x.abs() < velocity_y.abs() * 2.0
+                {
+                    return;
+                }
+
+                let action = if velocity_x > 0.0 {
+                    "dir.prev"
+                } else {
+                    "dir.next"
+                };
+                let _ = this.activate_action(action, None);
+            }
+        ));
+        widget.add_controller(swipe);
     }
 }
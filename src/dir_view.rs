@@ -10,19 +10,61 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::Signal;
 use glib_macros::{clone, Properties};
-use gtk::{gio, glib, CompositeTemplate};
+use gtk::{gdk, gio, glib, CompositeTemplate};
 use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::ptr::NonNull;
 use std::sync::OnceLock;
 
 use crate::{config::LOG_DOMAIN, file_selector::SortMode, grid_item::GridItem, util};
 
+// Key for the cached collation key on a `gio::FileInfo`
+const COLLATION_KEY: &str = "pfs-collation-key";
+
+// Attribute holding a search result's folder relative to the searched one
+pub const PARENT_FOLDER_ATTRIBUTE: &str = "pfs::parent-folder";
+
+const SEARCH_ATTRIBUTES: &str = "standard::name,standard::display-name,standard::icon,standard::content-type,standard::type,standard::is-hidden,standard::is-symlink,standard::size,time::modified,thumbnail::*";
+
+// How deep to descend into subfolders when searching
+const SEARCH_MAX_DEPTH: u32 = 5;
+
+// Stop searching once that many files matched
+const SEARCH_MAX_RESULTS: usize = 500;
+
+// How many files to fetch at once when searching
+const SEARCH_BATCH_SIZE: i32 = 64;
+
 #[derive(Debug, Copy, Clone, Default, PartialEq, gio::glib::Enum)]
 #[enum_type(name = "PfsDirViewThumbnailMode")]
 pub enum ThumbnailMode {
     #[default]
-    Never,
-    Local,
+    Never, // no thumbnails at all
+    Local,          // thumbnails for local files only
+    Always,         // thumbnails for local files and remote mounts
+    SmallFilesOnly, // thumbnails for files up to `thumbnail-max-size`
+}
+
+impl ThumbnailMode {
+    // Whether to show a thumbnail for `file` that is `size` bytes large
+    pub fn allows(self, file: &gio::File, size: u64, max_size: u64) -> bool {
+        match self {
+            ThumbnailMode::Never => false,
+            ThumbnailMode::Local => file.is_native(),
+            ThumbnailMode::Always => true,
+            // Fetching large files from remote locations is costly too
+            ThumbnailMode::SmallFilesOnly => size <= max_size,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, gio::glib::Enum)]
+#[enum_type(name = "PfsDirViewViewMode")]
+pub enum ViewMode {
+    #[default]
+    Grid, // icons in a grid
+    List, // rows with columns for details
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, gio::glib::Enum)]
@@ -44,12 +86,36 @@ mod imp {
         #[template_child]
         pub grid_view: TemplateChild<gtk::GridView>,
 
+        #[template_child]
+        pub column_view: TemplateChild<gtk::ColumnView>,
+
+        #[template_child]
+        pub name_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        #[template_child]
+        pub size_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        #[template_child]
+        pub type_column: TemplateChild<gtk::ColumnViewColumn>,
+
+        #[template_child]
+        pub modified_column: TemplateChild<gtk::ColumnViewColumn>,
+
         #[template_child]
         pub view_stack: TemplateChild<gtk::Stack>,
 
+        #[template_child]
+        pub mode_stack: TemplateChild<gtk::Stack>,
+
         #[template_child]
         pub directory_list: TemplateChild<gtk::DirectoryList>,
 
+        #[template_child]
+        pub search_results: TemplateChild<gio::ListStore>,
+
+        #[template_child]
+        pub type_filtered_list: TemplateChild<gtk::FilterListModel>,
+
         #[template_child]
         pub sorted_list: TemplateChild<gtk::SortListModel>,
 
@@ -59,6 +125,9 @@ mod imp {
         #[template_child]
         pub single_selection: TemplateChild<gtk::SingleSelection>,
 
+        #[template_child]
+        pub multi_selection: TemplateChild<gtk::MultiSelection>,
+
         #[template_child]
         pub item_factory: TemplateChild<gtk::SignalListItemFactory>,
 
@@ -77,6 +146,17 @@ mod imp {
         #[property(get, set = Self::set_search_term, explicit_notify)]
         pub(super) search_term: RefCell<Option<String>>,
 
+        // Whether the search includes subfolders
+        #[property(get, set = Self::set_search_subfolders, explicit_notify)]
+        pub(super) search_subfolders: Cell<bool>,
+
+        // Cancels the running subfolder search
+        pub(super) search_cancellable: RefCell<Option<gio::Cancellable>>,
+
+        // Whether to show a grid or a list
+        #[property(get, set = Self::set_view_mode, builder(ViewMode::default()))]
+        pub view_mode: Cell<ViewMode>,
+
         // Icon size of the items in the grid view
         #[property(get, set)]
         icon_size: Cell<u32>,
@@ -112,6 +192,25 @@ mod imp {
         // Whether to show thumbnails
         #[property(get, set, builder(ThumbnailMode::default()))]
         pub thumbnail_mode: RefCell<ThumbnailMode>,
+
+        // Size limit in bytes for `ThumbnailMode::SmallFilesOnly`
+        #[property(get, set)]
+        thumbnail_max_size: Cell<u64>,
+
+        // Whether multiple files can be selected
+        #[property(get, set = Self::set_multiple, explicit_notify)]
+        pub(super) multiple: Cell<bool>,
+
+        // Whether the user can create folders (e.g. when saving)
+        #[property(get, set)]
+        pub(super) can_create_folder: Cell<bool>,
+
+        // `true` while taps toggle items rather than replacing the selection
+        #[property(get, explicit_notify)]
+        pub(super) selection_mode: Cell<bool>,
+
+        // File to select once the folder finished loading
+        pub(super) pending_selection: RefCell<Option<gio::File>>,
     }
 
     #[glib::object_subclass]
@@ -125,6 +224,21 @@ mod imp {
             klass.bind_template_instance_callbacks();
 
             klass.set_accessible_role(gtk::AccessibleRole::Group);
+
+            // Explicit way out of selection mode besides deselecting everything
+            klass.add_binding(
+                gdk::Key::Escape,
+                gdk::ModifierType::NO_MODIFIER_MASK,
+                |this| {
+                    if !this.selection_mode() {
+                        return glib::Propagation::Proceed;
+                    }
+
+                    this.imp().multi_selection.unselect_all();
+                    this.imp().set_selection_mode(false);
+                    glib::Propagation::Stop
+                },
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -143,6 +257,44 @@ mod imp {
             self.obj().notify_has_selection();
         }
 
+        // r/o property
+        pub(super) fn set_selection_mode(&self, selection_mode: bool) {
+            if selection_mode == self.selection_mode.get() {
+                return;
+            }
+
+            glib::g_debug!(LOG_DOMAIN, "selection_mode {selection_mode:#?}");
+            self.selection_mode.replace(selection_mode);
+            self.obj().notify_selection_mode();
+        }
+
+        fn set_multiple(&self, multiple: bool) {
+            let obj = self.obj();
+
+            if self.multiple.get() == multiple {
+                return;
+            }
+
+            glib::g_debug!(LOG_DOMAIN, "multiple {multiple:#?}");
+
+            self.single_selection.unselect_all();
+            self.multi_selection.unselect_all();
+            self.set_selection_mode(false);
+
+            self.multiple.replace(multiple);
+            if multiple {
+                self.grid_view.set_model(Some(&self.multi_selection.get()));
+                self.column_view
+                    .set_model(Some(&self.multi_selection.get()));
+            } else {
+                self.grid_view.set_model(Some(&self.single_selection.get()));
+                self.column_view
+                    .set_model(Some(&self.single_selection.get()));
+            }
+
+            obj.notify_multiple();
+        }
+
         fn update_directory_selection(&self) {
             // In directory selection mode we have a selection whenever
             // we're in a valid dir (e.g. not in recent:///
@@ -173,7 +325,20 @@ mod imp {
             *self.folder.borrow_mut() = Some(folder);
             obj.notify_folder();
 
+            self.set_selection_mode(false);
             self.update_directory_selection();
+            obj.update_search();
+        }
+
+        fn set_view_mode(&self, mode: ViewMode) {
+            glib::g_debug!(LOG_DOMAIN, "view_mode {mode:#?}");
+
+            self.view_mode.replace(mode);
+            let name = match mode {
+                ViewMode::Grid => "grid",
+                ViewMode::List => "list",
+            };
+            self.mode_stack.set_visible_child_name(name);
         }
 
         fn set_show_hidden(&self, show_hidden: bool) {
@@ -195,6 +360,9 @@ mod imp {
                 false => gtk::FilterChange::MoreStrict,
             };
             filter.emit_by_name::<()>("changed", &[&strict]);
+
+            // Hidden folders are only searched when shown
+            obj.update_search();
         }
 
         fn set_sort_mode(&self, mode: SortMode) {
@@ -317,6 +485,19 @@ mod imp {
             let filter = self.filtered_list.filter().unwrap();
             filter.emit_by_name::<()>("changed", &[&strict]);
             obj.notify_search_term();
+            obj.update_search();
+        }
+
+        fn set_search_subfolders(&self, search_subfolders: bool) {
+            if self.search_subfolders.get() == search_subfolders {
+                return;
+            }
+
+            glib::g_debug!(LOG_DOMAIN, "search_subfolders {search_subfolders:#?}");
+
+            self.search_subfolders.replace(search_subfolders);
+            self.obj().notify_search_subfolders();
+            self.obj().update_search();
         }
     }
 
@@ -329,6 +510,7 @@ mod imp {
             obj.setup_gsettings();
             obj.set_directories_first(true);
             obj.setup_sort_and_filter();
+            obj.setup_columns();
             obj.on_n_items_changed();
 
             obj.bind_property("folder", &self.directory_list.get(), "file")
@@ -354,6 +536,7 @@ mod imp {
     }
 
     impl WidgetImpl for DirView {}
+
     impl BinImpl for DirView {}
 }
 
@@ -374,6 +557,11 @@ impl DirView {
         Self::default()
     }
 
+    // The grid of files, e.g. for attaching gestures
+    pub fn grid_view(&self) -> gtk::GridView {
+        self.imp().grid_view.get()
+    }
+
     fn is_directory(&self, fileinfo: &gio::FileInfo) -> bool {
         let content_type = fileinfo.content_type().unwrap_or_default();
 
@@ -396,9 +584,65 @@ impl DirView {
             .sync_create()
             .build();
 
+        self.bind_property("thumbnail-max-size", &grid_item, "thumbnail-max-size")
+            .sync_create()
+            .build();
+
+        self.add_selection_gestures(&grid_item, list_item);
+
         list_item.set_child(Some(&grid_item));
     }
 
+    // Touch friendly multi selection: long press picks the first file and
+    // taps toggle further ones
+    fn add_selection_gestures(&self, widget: &impl IsA<gtk::Widget>, list_item: &gtk::ListItem) {
+        // Long press starts selection mode when multiple files can be selected
+        let long_press = gtk::GestureLongPress::new();
+        long_press.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            list_item,
+            move |gesture, _, _| {
+                if !this.multiple() || this.selection_mode() {
+                    return;
+                }
+
+                // Folders can't be selected, don't get stuck in selection mode
+                // without a selection
+                let pos = list_item.position();
+                if !this.is_selectable(pos) {
+                    return;
+                }
+
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                this.imp().multi_selection.unselect_all();
+                this.imp().set_selection_mode(true);
+                this.toggle_selected(pos);
+            }
+        ));
+        widget.add_controller(long_press);
+
+        // In selection mode a tap toggles the item instead of replacing the selection
+        let click = gtk::GestureClick::new();
+        click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        click.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            list_item,
+            move |gesture, _, _, _| {
+                if !this.selection_mode() {
+                    return;
+                }
+
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                this.toggle_selected(list_item.position());
+            }
+        ));
+        widget.add_controller(click);
+    }
+
     #[template_callback]
     fn on_item_bind(&self, object: glib::Object) {
         let list_item = object.downcast_ref::<gtk::ListItem>().unwrap();
@@ -411,17 +655,143 @@ impl DirView {
         grid_item.set_fileinfo(info);
     }
 
+    #[template_callback]
+    fn on_item_unbind(&self, object: glib::Object) {
+        let list_item = object.downcast_ref::<gtk::ListItem>().unwrap();
+
+        let widget = list_item.child().unwrap();
+        let grid_item = widget.downcast_ref::<GridItem>().unwrap();
+
+        grid_item.unbind();
+    }
+
+    // The file info of a list view cell
+    fn cell_fileinfo(&self, object: &glib::Object) -> (gtk::ListItem, gio::FileInfo) {
+        let list_item = object.downcast_ref::<gtk::ListItem>().unwrap();
+        let item = list_item.item().unwrap();
+        let info = item.downcast::<gio::FileInfo>().unwrap();
+
+        (list_item.clone(), info)
+    }
+
+    fn set_cell_text(&self, list_item: &gtk::ListItem, text: &str) {
+        let widget = list_item.child().unwrap();
+        let label = widget.downcast_ref::<gtk::Label>().unwrap();
+
+        label.set_label(text);
+    }
+
+    #[template_callback]
+    fn on_name_cell_setup(&self, object: glib::Object) {
+        let list_item = object.downcast_ref::<gtk::ListItem>().unwrap();
+
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        content.append(&gtk::Image::new());
+
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        labels.set_valign(gtk::Align::Center);
+        labels.append(
+            &gtk::Label::builder()
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::Middle)
+                .build(),
+        );
+        // The folder of a search result
+        labels.append(
+            &gtk::Label::builder()
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::Start)
+                .css_classes(["caption", "dim-label"])
+                .visible(false)
+                .build(),
+        );
+        content.append(&labels);
+        self.add_selection_gestures(&content, list_item);
+        list_item.set_child(Some(&content));
+    }
+
+    #[template_callback]
+    fn on_name_cell_bind(&self, object: glib::Object) {
+        let (list_item, info) = self.cell_fileinfo(&object);
+        let content = list_item.child().unwrap();
+
+        let image = content.first_child().and_downcast::<gtk::Image>().unwrap();
+        match info.icon() {
+            Some(icon) => image.set_from_gicon(&icon),
+            None => image.clear(),
+        }
+
+        let labels = content.last_child().unwrap();
+        let label = labels.first_child().and_downcast::<gtk::Label>().unwrap();
+        label.set_label(&info.display_name());
+
+        let subtitle = labels.last_child().and_downcast::<gtk::Label>().unwrap();
+        let parent_folder = info.attribute_string(PARENT_FOLDER_ATTRIBUTE);
+        subtitle.set_visible(parent_folder.is_some());
+        subtitle.set_label(parent_folder.as_ref().map_or("", |folder| folder.as_str()));
+    }
+
+    #[template_callback]
+    fn on_text_cell_setup(&self, object: glib::Object) {
+        let list_item = object.downcast_ref::<gtk::ListItem>().unwrap();
+        let label = gtk::Label::builder()
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .css_classes(["dim-label"])
+            .build();
+
+        self.add_selection_gestures(&label, list_item);
+        list_item.set_child(Some(&label));
+    }
+
+    #[template_callback]
+    fn on_size_cell_bind(&self, object: glib::Object) {
+        let (list_item, info) = self.cell_fileinfo(&object);
+
+        // Folder sizes would need a recursive walk
+        let size = if self.is_directory(&info) {
+            String::new()
+        } else {
+            glib::format_size(info.size() as u64).to_string()
+        };
+        self.set_cell_text(&list_item, &size);
+    }
+
+    #[template_callback]
+    fn on_type_cell_bind(&self, object: glib::Object) {
+        let (list_item, info) = self.cell_fileinfo(&object);
+
+        let description = info
+            .content_type()
+            .map(|content_type| gio::content_type_get_description(&content_type).to_string())
+            .unwrap_or_default();
+        self.set_cell_text(&list_item, &description);
+    }
+
+    #[template_callback]
+    fn on_modified_cell_bind(&self, object: glib::Object) {
+        let (list_item, info) = self.cell_fileinfo(&object);
+
+        let modified = info
+            .modification_date_time()
+            .and_then(|date| date.to_local().ok())
+            .and_then(|date| date.format("%x %R").ok())
+            .map(|date| date.to_string())
+            .unwrap_or_default();
+        self.set_cell_text(&list_item, &modified);
+    }
+
     #[template_callback]
     fn on_selection_changed(&self, position: u32, n_items: u32) {
         glib::g_debug!(LOG_DOMAIN, "Selection changed {position:#?} {n_items:#?}");
 
-        let selection = self.imp().single_selection.get();
-        let selected_item = selection.selected_item();
+        let selection = self.selection_model();
+        let selected = selection.selection();
         let mut is_selected = false;
 
-        if selected_item.is_some() {
-            let info = selected_item.unwrap();
-            let fileinfo = info.downcast_ref::<gio::FileInfo>().unwrap();
+        if selected.size() == 1 {
+            let item = selection.item(selected.nth(0)).unwrap();
+            let fileinfo = item.downcast_ref::<gio::FileInfo>().unwrap();
             let object = fileinfo.attribute_object("standard::file").unwrap();
             let file = object.downcast_ref::<gio::File>().unwrap();
 
@@ -432,11 +802,18 @@ impl DirView {
                 self.imp().obj().emit_by_name::<()>("new-uri", &[&uri]);
             } else {
                 is_selected = true;
-                let filename = file.basename();
+                let filename = util::file_display_name(file);
                 self.imp()
                     .obj()
                     .emit_by_name::<()>("new-filename", &[&filename]);
             }
+        } else if selected.size() > 1 {
+            is_selected = true;
+        }
+
+        // Leave selection mode once the last item got deselected
+        if self.selection_mode() && selected.is_empty() {
+            self.imp().set_selection_mode(false);
         }
 
         if self.directories_only() {
@@ -457,7 +834,10 @@ impl DirView {
     fn on_activate(&self, pos: u32) {
         glib::g_debug!(LOG_DOMAIN, "Item Activated {pos:#?}");
 
-        self.imp().single_selection.set_selected(pos);
+        // In selection mode activation accepts the current selection
+        if !self.selection_mode() {
+            self.selection_model().select_item(pos, true);
+        }
         // Only accept when we have a selection
         if !self.has_selection() {
             return;
@@ -497,6 +877,15 @@ impl DirView {
         }
         self.imp().display_mode.replace(mode);
         self.imp().obj().notify_display_mode();
+
+        if mode == DisplayMode::Content {
+            self.select_pending();
+        }
+    }
+
+    #[template_callback]
+    fn status_page_new_folder_visible(&self, mode: DisplayMode, can_create_folder: bool) -> bool {
+        can_create_folder && mode == DisplayMode::Content
     }
 
     #[template_callback]
@@ -507,6 +896,79 @@ impl DirView {
         }
     }
 
+    fn selection_model(&self) -> gtk::SelectionModel {
+        if self.multiple() {
+            self.imp().multi_selection.get().upcast()
+        } else {
+            self.imp().single_selection.get().upcast()
+        }
+    }
+
+    // Only files can be picked, folders are for browsing
+    fn is_selectable(&self, pos: u32) -> bool {
+        self.imp()
+            .multi_selection
+            .item(pos)
+            .and_downcast::<gio::FileInfo>()
+            .is_some_and(|info| !self.is_directory(&info))
+    }
+
+    fn toggle_selected(&self, pos: u32) {
+        let selection = self.imp().multi_selection.get();
+        if !self.is_selectable(pos) {
+            return;
+        }
+
+        if selection.is_selected(pos) {
+            selection.unselect_item(pos);
+        } else {
+            selection.select_item(pos, false);
+        }
+    }
+
+    // Select `file` in the current folder, waiting for it to load if needed
+    pub fn select_file(&self, file: &gio::File) {
+        *self.imp().pending_selection.borrow_mut() = Some(file.clone());
+
+        if !self.imp().directory_list.is_loading() {
+            self.select_pending();
+        }
+    }
+
+    fn select_pending(&self) {
+        let Some(file) = self.imp().pending_selection.take() else {
+            return;
+        };
+
+        let selection = self.selection_model();
+        for pos in 0..selection.n_items() {
+            let item = selection.item(pos).unwrap();
+            let fileinfo = item.downcast_ref::<gio::FileInfo>().unwrap();
+            let object = fileinfo.attribute_object("standard::file").unwrap();
+
+            if !object.downcast_ref::<gio::File>().unwrap().equal(&file) {
+                continue;
+            }
+
+            selection.select_item(pos, true);
+            match self.view_mode() {
+                ViewMode::Grid => {
+                    self.imp()
+                        .grid_view
+                        .scroll_to(pos, gtk::ListScrollFlags::FOCUS, None)
+                }
+                ViewMode::List => {
+                    self.imp()
+                        .column_view
+                        .scroll_to(pos, None, gtk::ListScrollFlags::FOCUS, None)
+                }
+            }
+            return;
+        }
+
+        glib::g_debug!(LOG_DOMAIN, "{:#?} not found in folder", file.uri());
+    }
+
     pub fn selected(&self) -> Option<Vec<String>> {
         let vec = if self.directories_only() {
             match self.folder().unwrap().path() {
@@ -514,71 +976,100 @@ impl DirView {
                 Some(_) => vec![self.folder().unwrap().uri().to_string()],
             }
         } else {
-            let selected = self.imp().single_selection.get().selected_item();
-            let item = match selected {
-                None => return None,
-                Some(item) => item,
-            };
+            let selection = self.selection_model();
+            let selected = selection.selection();
+            let mut uris = Vec::new();
 
-            let file = item
-                .downcast_ref::<gio::FileInfo>()
-                .unwrap()
-                .attribute_object("standard::file")
-                .unwrap();
+            for n in 0..selected.size() {
+                let item = selection.item(selected.nth(n as u32)).unwrap();
+                let fileinfo = item.downcast_ref::<gio::FileInfo>().unwrap();
 
-            let uri = file.downcast_ref::<gio::File>().unwrap().uri();
-            glib::g_debug!(LOG_DOMAIN, "Uri {uri:#?}");
+                if self.multiple() && self.is_directory(fileinfo) {
+                    continue;
+                }
 
-            vec![uri.to_string()]
+                let file = fileinfo.attribute_object("standard::file").unwrap();
+                let uri = file.downcast_ref::<gio::File>().unwrap().uri();
+                glib::g_debug!(LOG_DOMAIN, "Uri {uri:#?}");
+
+                uris.push(uri.to_string());
+            }
+
+            if uris.is_empty() {
+                return None;
+            }
+            uris
         };
         Some(vec)
     }
 
-    fn sort_by_name(&self, info1: &gio::FileInfo, info2: &gio::FileInfo) -> gtk::Ordering {
-        match info1.display_name().cmp(&info2.display_name()) {
-            Ordering::Less => {
-                if self.imp().reversed.get() {
-                    return gtk::Ordering::Larger;
-                } else {
-                    return gtk::Ordering::Smaller;
-                }
-            }
-            Ordering::Greater => {
-                if self.imp().reversed.get() {
-                    return gtk::Ordering::Smaller;
-                } else {
-                    return gtk::Ordering::Larger;
-                }
+    // Turn `ordering` into a `gtk::Ordering` honoring `reversed`
+    fn to_sort_order(&self, ordering: Ordering) -> gtk::Ordering {
+        let ordering = if self.imp().reversed.get() {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+
+        gtk::Ordering::from(ordering)
+    }
+
+    // Locale aware key that sorts `file2` before `file10`. Computing it is
+    // expensive so it's cached on the item.
+    fn collation_key(info: &gio::FileInfo) -> NonNull<glib::FilenameCollationKey> {
+        // SAFETY: The data is only ever set here and always of the same type
+        unsafe {
+            if let Some(key) = info.data::<glib::FilenameCollationKey>(COLLATION_KEY) {
+                return key;
             }
-            Ordering::Equal => gtk::Ordering::Equal,
+
+            let key = glib::FilenameCollationKey::from(info.display_name());
+            info.set_data(COLLATION_KEY, key);
+            info.data(COLLATION_KEY).unwrap()
         }
     }
 
+    fn compare_names(&self, info1: &gio::FileInfo, info2: &gio::FileInfo) -> Ordering {
+        let key1 = Self::collation_key(info1);
+        let key2 = Self::collation_key(info2);
+
+        // SAFETY: The keys live as long as the items which we hold
+        unsafe { key1.as_ref().cmp(key2.as_ref()) }
+    }
+
+    fn sort_by_name(&self, info1: &gio::FileInfo, info2: &gio::FileInfo) -> gtk::Ordering {
+        self.to_sort_order(self.compare_names(info1, info2))
+    }
+
     fn sort_by_modification_time(
         &self,
         info1: &gio::FileInfo,
         info2: &gio::FileInfo,
     ) -> gtk::Ordering {
-        match info1
+        let ordering = info1
             .modification_date_time()
             .cmp(&info2.modification_date_time())
-        {
-            Ordering::Less => {
-                if self.imp().reversed.get() {
-                    return gtk::Ordering::Larger;
-                } else {
-                    return gtk::Ordering::Smaller;
-                }
-            }
-            Ordering::Greater => {
-                if self.imp().reversed.get() {
-                    return gtk::Ordering::Smaller;
-                } else {
-                    return gtk::Ordering::Larger;
-                }
-            }
-            Ordering::Equal => gtk::Ordering::Equal,
-        }
+            .then_with(|| self.compare_names(info1, info2));
+
+        self.to_sort_order(ordering)
+    }
+
+    fn sort_by_size(&self, info1: &gio::FileInfo, info2: &gio::FileInfo) -> gtk::Ordering {
+        let ordering = info1
+            .size()
+            .cmp(&info2.size())
+            .then_with(|| self.compare_names(info1, info2));
+
+        self.to_sort_order(ordering)
+    }
+
+    fn sort_by_type(&self, info1: &gio::FileInfo, info2: &gio::FileInfo) -> gtk::Ordering {
+        let ordering = info1
+            .content_type()
+            .cmp(&info2.content_type())
+            .then_with(|| self.compare_names(info1, info2));
+
+        self.to_sort_order(ordering)
     }
 
     fn setup_sort_and_filter(&self) {
@@ -612,6 +1103,8 @@ impl DirView {
                 match mode {
                     SortMode::DisplayName => this.sort_by_name(&info1, &info2),
                     SortMode::ModificationTime => this.sort_by_modification_time(&info1, &info2),
+                    SortMode::Size => this.sort_by_size(&info1, &info2),
+                    SortMode::Type => this.sort_by_type(&info1, &info2),
                 }
             }
         ));
@@ -628,13 +1121,7 @@ impl DirView {
                     .expect("Should be file info");
                 let search_term = this.imp().search_term.borrow();
 
-                if search_term.is_some()
-                    && !info
-                        .display_name()
-                        .trim()
-                        .to_lowercase()
-                        .starts_with(search_term.as_ref().unwrap())
-                {
+                if search_term.is_some() && !matches_search(info, search_term.as_ref().unwrap()) {
                     return false;
                 }
 
@@ -655,12 +1142,228 @@ impl DirView {
         self.imp().filtered_list.set_filter(Some(&custom_filter));
     }
 
+    fn column_to_sort_mode(&self, column: &gtk::ColumnViewColumn) -> Option<SortMode> {
+        let imp = self.imp();
+
+        if *column == *imp.name_column {
+            Some(SortMode::DisplayName)
+        } else if *column == *imp.modified_column {
+            Some(SortMode::ModificationTime)
+        } else if *column == *imp.size_column {
+            Some(SortMode::Size)
+        } else if *column == *imp.type_column {
+            Some(SortMode::Type)
+        } else {
+            None
+        }
+    }
+
+    fn sort_mode_to_column(&self, mode: SortMode) -> gtk::ColumnViewColumn {
+        match mode {
+            SortMode::DisplayName => self.imp().name_column.get(),
+            SortMode::ModificationTime => self.imp().modified_column.get(),
+            SortMode::Size => self.imp().size_column.get(),
+            SortMode::Type => self.imp().type_column.get(),
+        }
+    }
+
+    fn setup_columns(&self) {
+        let imp = self.imp();
+
+        // The columns' sorters only make the headers clickable. Sorting happens
+        // in `sorted_list` so grid and list view always agree.
+        for column in [
+            &imp.name_column,
+            &imp.size_column,
+            &imp.type_column,
+            &imp.modified_column,
+        ] {
+            column.set_sorter(Some(&gtk::CustomSorter::new(|_, _| gtk::Ordering::Equal)));
+        }
+
+        let sorter = imp
+            .column_view
+            .sorter()
+            .and_downcast::<gtk::ColumnViewSorter>()
+            .unwrap();
+        sorter.connect_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |sorter, _| {
+                let Some(mode) = sorter
+                    .primary_sort_column()
+                    .and_then(|column| this.column_to_sort_mode(&column))
+                else {
+                    return;
+                };
+                let reversed = sorter.primary_sort_order() == gtk::SortType::Descending;
+
+                if mode == this.sort_mode() && reversed == this.reversed() {
+                    return;
+                }
+
+                // Go via the action so the choice gets stored
+                let value = mode.to_value();
+                let (_, enum_value) = glib::EnumValue::from_value(&value).unwrap();
+                let param = (enum_value.nick(), reversed).to_variant();
+                if this
+                    .activate_action("file-selector.sort", Some(&param))
+                    .is_err()
+                {
+                    this.set_sorting(mode, reversed);
+                }
+            }
+        ));
+        self.update_sort_column();
+    }
+
+    // Reflect the sorting in the column headers
+    fn update_sort_column(&self) {
+        let column = self.sort_mode_to_column(self.sort_mode());
+        let order = if self.reversed() {
+            gtk::SortType::Descending
+        } else {
+            gtk::SortType::Ascending
+        };
+
+        self.imp().column_view.sort_by_column(Some(&column), order);
+    }
+
     fn setup_gsettings(&self) {
         let settings = gio::Settings::new("mobi.phosh.FileSelector");
         settings.bind("icon-size", self, "icon-size").build();
         settings
             .bind("thumbnail-mode", self, "thumbnail-mode")
             .build();
+        settings
+            .bind("thumbnail-max-size", self, "thumbnail-max-size")
+            .build();
+        settings.bind("view-mode", self, "view-mode").build();
+    }
+
+    // (Re)start searching subfolders if enabled, otherwise show the folder's content
+    fn update_search(&self) {
+        let imp = self.imp();
+
+        if let Some(cancellable) = imp.search_cancellable.take() {
+            cancellable.cancel();
+        }
+        imp.search_results.remove_all();
+
+        let term = imp
+            .search_term
+            .borrow()
+            .clone()
+            .filter(|term| !term.is_empty());
+        let folder = self.folder();
+        let recursive = self.search_subfolders() && term.is_some() && folder.is_some();
+
+        let model = if recursive {
+            imp.search_results.get().upcast::<gio::ListModel>()
+        } else {
+            imp.directory_list.get().upcast::<gio::ListModel>()
+        };
+        if imp.type_filtered_list.model().as_ref() != Some(&model) {
+            imp.type_filtered_list.set_model(Some(&model));
+        }
+
+        if !recursive {
+            return;
+        }
+        let (Some(term), Some(folder)) = (term, folder) else {
+            return;
+        };
+
+        glib::g_debug!(LOG_DOMAIN, "Searching {:#?} for {term:#?}", folder.uri());
+        let cancellable = gio::Cancellable::new();
+        imp.search_cancellable.replace(Some(cancellable.clone()));
+        let walk = Self::search_walk(self.downgrade(), folder, term, self.show_hidden());
+        glib::spawn_future_local(async move {
+            let _ = gio::CancellableFuture::new(walk, cancellable).await;
+        });
+    }
+
+    // Walk the tree under `root` breadth first adding matches to `search_results`
+    // as they're found. Only holds a weak ref so the walk ends with the view.
+    async fn search_walk(
+        view: glib::WeakRef<DirView>,
+        root: gio::File,
+        term: String,
+        show_hidden: bool,
+    ) {
+        let mut folders = VecDeque::from([(root.clone(), 0)]);
+        let mut n_results = 0;
+
+        while let Some((folder, depth)) = folders.pop_front() {
+            let enumerator = match folder
+                .enumerate_children_future(
+                    SEARCH_ATTRIBUTES,
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::Priority::LOW,
+                )
+                .await
+            {
+                Ok(enumerator) => enumerator,
+                Err(err) => {
+                    glib::g_debug!(LOG_DOMAIN, "Can't search {:#?}: {err}", folder.uri());
+                    continue;
+                }
+            };
+            let parent_folder = root
+                .relative_path(&folder)
+                .map(|path| glib::filename_display_name(path).to_string())
+                .unwrap_or_else(|| util::folder_to_name(root.clone()));
+
+            loop {
+                let infos = match enumerator
+                    .next_files_future(SEARCH_BATCH_SIZE, glib::Priority::LOW)
+                    .await
+                {
+                    Ok(infos) if !infos.is_empty() => infos,
+                    Ok(_) => break,
+                    Err(err) => {
+                        glib::g_debug!(LOG_DOMAIN, "Can't search {:#?}: {err}", folder.uri());
+                        break;
+                    }
+                };
+
+                let mut matches = Vec::new();
+                for info in infos {
+                    if !show_hidden && (info.is_hidden() || info.display_name().starts_with('.')) {
+                        continue;
+                    }
+
+                    let child = enumerator.child(&info);
+                    // Symlinks could send us around in circles
+                    if info.file_type() == gio::FileType::Directory
+                        && !info.is_symlink()
+                        && depth < SEARCH_MAX_DEPTH
+                    {
+                        folders.push_back((child.clone(), depth + 1));
+                    }
+
+                    if !matches_search(&info, &term) {
+                        continue;
+                    }
+
+                    info.set_attribute_object("standard::file", &child);
+                    info.set_attribute_string(PARENT_FOLDER_ATTRIBUTE, &parent_folder);
+                    matches.push(info);
+                }
+
+                let Some(view) = view.upgrade() else {
+                    return;
+                };
+                matches.truncate(SEARCH_MAX_RESULTS - n_results);
+                n_results += matches.len();
+                view.imp().search_results.extend_from_slice(&matches);
+
+                if n_results >= SEARCH_MAX_RESULTS {
+                    glib::g_debug!(LOG_DOMAIN, "Search hit {SEARCH_MAX_RESULTS} results");
+                    return;
+                }
+            }
+        }
     }
 
     pub fn set_sorting(&self, sort_mode: SortMode, reversed: bool) {
@@ -674,6 +1377,7 @@ impl DirView {
 
         self.notify_sort_mode();
         self.notify_reversed();
+        self.update_sort_column();
 
         // Resort
         let sorter = self.imp().sorted_list.sorter().unwrap();
@@ -681,3 +1385,8 @@ impl DirView {
         sorter.emit_by_name::<()>("changed", &[&change]);
     }
 }
+
+// Whether `info`'s name matches the (lower case) search `term`
+fn matches_search(info: &gio::FileInfo, term: &str) -> bool {
+    info.display_name().trim().to_lowercase().starts_with(term)
+}
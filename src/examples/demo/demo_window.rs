@@ -223,6 +223,7 @@ impl PfsDemoWindow {
             .property("accept-label", "Done")
             .property("title", "Save Files")
             .property("current-folder", gio::File::for_path("/home"))
+            .property("files", glib::StrV::from(["newfile1.txt", "newfile2.txt"]))
             .build();
 
         file_selector.connect_closure(
@@ -12,11 +12,12 @@ use glib::translate::*;
 use glib_macros::{clone, Properties};
 use gtk::{gdk, gio, glib, CompositeTemplate};
 use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::sync::OnceLock;
 
 use crate::{
-    config::LOG_DOMAIN, dir_stack::DirStack, dir_view::DirView, places_box::PlacesBox,
-    stateful_action, util,
+    app_state::AppState, config::LOG_DOMAIN, dir_stack::DirStack, dir_view::DirView,
+    places_box::PlacesBox, save_item::SaveItem, stateful_action, util,
 };
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, gio::glib::Enum)]
@@ -36,6 +37,10 @@ pub enum SortMode {
     DisplayName = 0,
     #[enum_value(nick = "mtime")]
     ModificationTime = 1,
+    #[enum_value(nick = "size")]
+    Size = 2,
+    #[enum_value(nick = "type")]
+    Type = 3,
 }
 
 pub mod imp {
@@ -63,6 +68,12 @@ pub mod imp {
         #[template_child]
         pub bottom_sheet: TemplateChild<adw::BottomSheet>,
 
+        #[template_child]
+        pub split_view: TemplateChild<adw::OverlaySplitView>,
+
+        #[template_child]
+        pub places_view: TemplateChild<adw::ToolbarView>,
+
         #[template_child]
         pub choices_menu_button: TemplateChild<gtk::MenuButton>,
 
@@ -72,12 +83,43 @@ pub mod imp {
         #[template_child]
         pub search_entry: TemplateChild<gtk::SearchEntry>,
 
+        #[template_child]
+        pub files_list: TemplateChild<gtk::ListBox>,
+
+        #[template_child]
+        pub filename_entry: TemplateChild<gtk::Entry>,
+
+        #[template_child]
+        pub filename_status: TemplateChild<gtk::Label>,
+
         done: Cell<bool>,
 
+        // The file system type of the current folder
+        pub(super) fs_type: RefCell<Option<String>>,
+
+        pub(super) save_items: RefCell<Vec<SaveItem>>,
+
+        // `true` if there are files in SaveFiles mode the user needs to decide on
+        #[property(get, explicit_notify)]
+        pub(super) has_conflicts: Cell<bool>,
+
+        // `true` if the user skipped all files in SaveFiles mode
+        #[property(get, explicit_notify)]
+        pub(super) all_skipped: Cell<bool>,
+
         pub(super) choices_actions: RefCell<Option<gio::SimpleActionGroup>>,
 
+        pub(super) new_folder_action: RefCell<Option<gio::SimpleAction>>,
+
         pub(super) settings: RefCell<Option<gio::Settings>>,
 
+        // What we remember for the application using us, loaded when mapped
+        pub(super) state: RefCell<Option<AppState>>,
+
+        // The application the state is kept for
+        #[property(get, set)]
+        pub app_id: RefCell<String>,
+
         //
         // Properties mapping to the portal spec
         //
@@ -91,6 +133,10 @@ pub mod imp {
         #[property(get, set)]
         pub directory: Cell<bool>,
 
+        // Whether multiple files can be selected
+        #[property(get, set)]
+        pub multiple: Cell<bool>,
+
         // The filters
         #[property(get, set, construct)]
         pub filters: RefCell<Option<gio::ListModel>>,
@@ -119,6 +165,10 @@ pub mod imp {
         // The user selected choices
         #[property(get = Self::get_selected_choices, builder(glib::VariantTy::ARRAY))]
         pub selected_choices: RefCell<Option<glib::Variant>>,
+
+        // The file names (basenames) when saving multiple files
+        #[property(get, set = Self::set_files)]
+        pub files: RefCell<Vec<String>>,
     }
 
     #[glib::object_subclass]
@@ -142,6 +192,36 @@ pub mod imp {
                 gdk::ModifierType::NO_MODIFIER_MASK,
                 "window.close",
             );
+
+            klass.add_binding(gdk::Key::L, gdk::ModifierType::CONTROL_MASK, |this| {
+                this.edit_location(None);
+                glib::Propagation::Stop
+            });
+
+            // Typing the start of a path opens the location entry
+            for (key, text) in [(gdk::Key::slash, "/"), (gdk::Key::asciitilde, "~")] {
+                klass.add_binding(key, gdk::ModifierType::NO_MODIFIER_MASK, move |this| {
+                    let focus = gtk::prelude::GtkWindowExt::focus(this);
+                    if focus.is_some_and(|focus| focus.is::<gtk::Text>()) {
+                        return glib::Propagation::Proceed;
+                    }
+
+                    this.edit_location(Some(text));
+                    glib::Propagation::Stop
+                });
+            }
+
+            klass.add_binding_action(
+                gdk::Key::D,
+                gdk::ModifierType::CONTROL_MASK,
+                "file-selector.add-bookmark",
+            );
+
+            klass.add_binding_action(
+                gdk::Key::N,
+                gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                "file-selector.new-folder",
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -157,6 +237,17 @@ pub mod imp {
             let obj = self.obj();
             obj.setup_gsettings();
             obj.setup_gactions();
+            self.dir_stack.add_history_gestures(&*obj);
+            // Not the whole window as the path bar and list view scroll sideways
+            self.dir_stack.add_history_swipe(&self.dir_view.grid_view());
+            // The entry isn't the search bar's direct child
+            self.search_bar.connect_entry(&*self.search_entry);
+
+            obj.connect_current_folder_notify(|obj| {
+                obj.imp().update_fs_type();
+                obj.imp().remember_folder();
+                obj.update_new_folder_action();
+            });
         }
 
         fn signals() -> &'static [Signal] {
@@ -169,7 +260,16 @@ pub mod imp {
         }
     }
 
-    impl WidgetImpl for FileSelector {}
+    impl WidgetImpl for FileSelector {
+        fn map(&self) {
+            if self.state.borrow().is_none() {
+                self.restore_state();
+            }
+
+            self.parent_map();
+        }
+    }
+
     impl WindowImpl for FileSelector {}
     impl AdwWindowImpl for FileSelector {}
 
@@ -180,6 +280,7 @@ pub mod imp {
                 glib::g_debug!(LOG_DOMAIN, "Done, success: {success:#?}");
                 self.obj().emit_by_name::<()>("done", &[&success]);
                 self.done.replace(true);
+                self.save_state(success);
             }
 
             if close {
@@ -187,6 +288,115 @@ pub mod imp {
             }
         }
 
+        // Seed folder, history and filter from the application's last use
+        fn restore_state(&self) {
+            let obj = self.obj();
+            let state = AppState::load(&obj.app_id());
+
+            if obj.current_folder().is_none() {
+                match state.last_folder.as_ref() {
+                    Some(uri) => {
+                        let folder = gio::File::for_uri(uri);
+                        obj.set_current_folder(folder.clone());
+                        self.check_last_folder(folder);
+                    }
+                    None => obj.set_current_folder(gio::File::for_path(glib::home_dir())),
+                }
+            }
+
+            // Deleted folders get dropped by the dir stack
+            let history = state
+                .recent_folders
+                .iter()
+                .map(|uri| gio::File::for_uri(uri))
+                .collect::<Vec<_>>();
+            self.dir_stack.set_history(&history);
+
+            if let (Some(name), Some(filters)) = (state.last_filter.as_ref(), obj.filters()) {
+                if obj.current_filter() == gtk::INVALID_LIST_POSITION {
+                    let pos = filters.iter::<gtk::FileFilter>().position(|filter| {
+                        filter.is_ok_and(|filter| filter.name().as_deref() == Some(name.as_str()))
+                    });
+                    if let Some(pos) = pos {
+                        obj.set_current_filter(pos as u32);
+                    }
+                }
+            }
+
+            self.state.replace(Some(state));
+            // Folders visited before the state got loaded count too
+            self.remember_folder();
+        }
+
+        // Fall back to the home folder when the last used `folder` is gone.
+        // Checked in the background as it might be on a slow or remote file system.
+        fn check_last_folder(&self, folder: gio::File) {
+            glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let Err(err) = folder
+                        .query_info_future(
+                            gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+                            gio::FileQueryInfoFlags::NONE,
+                            glib::Priority::DEFAULT,
+                        )
+                        .await
+                    else {
+                        return;
+                    };
+
+                    let obj = this.obj();
+                    // The user moved on already
+                    if !obj
+                        .current_folder()
+                        .is_some_and(|current| current.equal(&folder))
+                    {
+                        return;
+                    }
+
+                    glib::g_debug!(
+                        LOG_DOMAIN,
+                        "Last folder {:#?} unusable: {err}",
+                        folder.uri()
+                    );
+                    obj.set_current_folder(gio::File::for_path(glib::home_dir()));
+                }
+            ));
+        }
+
+        fn remember_folder(&self) {
+            let Some(folder) = self.obj().current_folder() else {
+                return;
+            };
+
+            if let Some(state) = self.state.borrow_mut().as_mut() {
+                state.add_recent_folder(&folder.uri());
+            }
+        }
+
+        fn save_state(&self, success: bool) {
+            let obj = self.obj();
+            let mut binding = self.state.borrow_mut();
+            let Some(state) = binding.as_mut() else {
+                return;
+            };
+
+            if success {
+                state.last_folder = obj.current_folder().map(|folder| folder.uri().to_string());
+                state.last_filter = obj
+                    .filters()
+                    .and_then(|filters| filters.item(obj.current_filter()))
+                    .and_downcast::<gtk::FileFilter>()
+                    .and_then(|filter| filter.name())
+                    .map(|name| name.to_string());
+            }
+
+            if let Err(err) = state.save() {
+                glib::g_warning!(LOG_DOMAIN, "Failed to save state: {err}");
+            }
+        }
+
         fn set_current_filter(&self, pos: u32) {
             let obj = self.obj();
 
@@ -217,10 +427,159 @@ pub mod imp {
                 return;
             }
 
-            // TODO: check validity
-
+            // Keep invalid names so the user can fix them, accepting is
+            // blocked until the name is valid
             *self.filename.borrow_mut() = filename;
             obj.notify_filename();
+            self.update_filename_status();
+        }
+
+        pub(super) fn update_filename_status(&self) {
+            let entry = self.filename_entry.get();
+            let status = self.filename_status.get();
+            let filename = self.filename.borrow();
+
+            for widget in [entry.upcast_ref::<gtk::Widget>(), status.upcast_ref()] {
+                widget.remove_css_class("error");
+                widget.remove_css_class("warning");
+            }
+
+            let (icon_name, message) = match util::validate_filename(&filename) {
+                Err(util::FilenameError::Empty) => (None, None),
+                Err(err) => {
+                    entry.add_css_class("error");
+                    status.add_css_class("error");
+                    (Some("dialog-error-symbolic"), Some(err.message()))
+                }
+                Ok(()) => {
+                    let fs_type = self.fs_type.borrow();
+                    match util::filename_warning(&filename, fs_type.as_deref()) {
+                        Some(warning) => {
+                            entry.add_css_class("warning");
+                            status.add_css_class("warning");
+                            (Some("dialog-warning-symbolic"), Some(warning))
+                        }
+                        None => (None, None),
+                    }
+                }
+            };
+
+            entry.set_icon_from_icon_name(gtk::EntryIconPosition::Secondary, icon_name);
+            entry.set_icon_tooltip_text(gtk::EntryIconPosition::Secondary, message.as_deref());
+            // Tooltips aren't reachable on touch screens
+            status.set_label(message.as_deref().unwrap_or_default());
+            status.set_visible(message.is_some());
+        }
+
+        pub(super) fn update_fs_type(&self) {
+            self.fs_type.replace(None);
+            self.update_filename_status();
+
+            let Some(folder) = self.obj().current_folder() else {
+                return;
+            };
+
+            folder.query_filesystem_info_async(
+                "filesystem::type",
+                glib::Priority::DEFAULT,
+                None::<&gio::Cancellable>,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |result| {
+                        let fs_type = result
+                            .ok()
+                            .and_then(|info| info.attribute_string("filesystem::type"))
+                            .map(|fs_type| fs_type.to_string());
+
+                        glib::g_debug!(LOG_DOMAIN, "File system type {fs_type:#?}");
+                        this.fs_type.replace(fs_type);
+                        this.update_filename_status();
+                    }
+                ),
+            );
+        }
+
+        fn set_files(&self, files: Vec<String>) {
+            let obj = self.obj();
+
+            self.files_list.remove_all();
+            let mut items = Vec::new();
+            for name in files.iter() {
+                let item = SaveItem::new(name);
+
+                obj.bind_property("current-folder", &item, "folder")
+                    .sync_create()
+                    .build();
+                // Skipping depends on the action and whether the file exists
+                for property in ["resolved", "action", "exists"] {
+                    item.connect_notify_local(
+                        Some(property),
+                        clone!(
+                            #[weak(rename_to = this)]
+                            self,
+                            move |_, _| {
+                                this.update_conflicts();
+                            }
+                        ),
+                    );
+                }
+                item.connect_changed(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |_| {
+                        this.update_conflicts();
+                    }
+                ));
+
+                self.files_list.append(&item);
+                items.push(item);
+            }
+
+            *self.save_items.borrow_mut() = items;
+            *self.files.borrow_mut() = files;
+            obj.notify_files();
+            self.update_conflicts();
+        }
+
+        fn update_conflicts(&self) {
+            self.update_duplicates();
+            let has_conflicts = self.save_items.borrow().iter().any(|item| !item.resolved());
+            let all_skipped = {
+                let items = self.save_items.borrow();
+                !items.is_empty() && items.iter().all(|item| item.skipped())
+            };
+
+            if self.has_conflicts.get() != has_conflicts {
+                self.has_conflicts.replace(has_conflicts);
+                self.obj().notify_has_conflicts();
+            }
+
+            if self.all_skipped.get() != all_skipped {
+                self.all_skipped.replace(all_skipped);
+                self.obj().notify_all_skipped();
+            }
+        }
+
+        // Flag items that would be saved to the same file as another one
+        fn update_duplicates(&self) {
+            let items = self.save_items.borrow().clone();
+            let folder = self.obj().current_folder();
+            let target = |item: &SaveItem| match folder.as_ref() {
+                Some(folder) => item.file(folder).uri().to_string(),
+                None => item.text().to_string(),
+            };
+
+            let mut counts = std::collections::HashMap::new();
+            for item in items.iter().filter(|item| !item.skipped()) {
+                *counts.entry(target(item)).or_insert(0) += 1;
+            }
+
+            for item in items.iter() {
+                let duplicate =
+                    !item.skipped() && counts.get(&target(item)).is_some_and(|n| *n > 1);
+                item.set_duplicate(duplicate);
+            }
         }
 
         fn set_mode(&self, mode: FileSelectorMode) {
@@ -245,6 +604,7 @@ pub mod imp {
 
             *self.mode.borrow_mut() = mode;
             obj.notify_mode();
+            obj.update_new_folder_action();
         }
 
         fn set_choices_menu(&self, actions: gio::SimpleActionGroup, menu: &gio::Menu) {
@@ -373,6 +733,36 @@ pub mod imp {
             self.search_entry.set_text("");
         }
 
+        #[template_callback]
+        fn on_select_uri(&self, uri: String) {
+            let file = gio::File::for_uri(&uri);
+            let Some(parent) = file.parent() else {
+                return;
+            };
+
+            self.on_new_uri(parent.uri().to_string());
+            self.dir_view.select_file(&file);
+        }
+
+        // Wide windows show the places in a sidebar instead of the bottom sheet
+        #[template_callback]
+        fn on_breakpoint_apply(&self) {
+            // Keep a reference while moving it over
+            let places_view = self.places_view.get();
+
+            self.bottom_sheet.set_open(false);
+            self.bottom_sheet.set_sheet(None::<&gtk::Widget>);
+            self.split_view.set_sidebar(Some(&places_view));
+        }
+
+        #[template_callback]
+        fn on_breakpoint_unapply(&self) {
+            let places_view = self.places_view.get();
+
+            self.split_view.set_sidebar(None::<&gtk::Widget>);
+            self.bottom_sheet.set_sheet(Some(&places_view));
+        }
+
         #[template_callback]
         fn on_new_filename(&self, filename: String) {
             if self.obj().mode() != FileSelectorMode::SaveFile {
@@ -412,7 +802,7 @@ pub mod imp {
                 return "".to_string();
             };
             let path = file.path().unwrap_or_default();
-            path.to_str().unwrap_or_default().to_string()
+            glib::filename_display_name(path).to_string()
         }
 
         #[template_callback]
@@ -452,15 +842,33 @@ pub mod imp {
             folder: Option<gio::File>,
             has_selection: bool,
             text: &str,
+            has_conflicts: bool,
+            all_skipped: bool,
         ) -> bool {
-            if self.obj().mode() == FileSelectorMode::SaveFile {
-                if text.is_empty() {
-                    return false;
+            match self.obj().mode() {
+                FileSelectorMode::SaveFile => {
+                    if util::validate_filename(text).is_err() {
+                        return false;
+                    }
+
+                    util::is_valid_folder(&folder)
                 }
+                FileSelectorMode::SaveFiles => has_selection && !has_conflicts && !all_skipped,
+                FileSelectorMode::OpenFile => has_selection,
+            }
+        }
 
-                util::is_valid_folder(&folder)
-            } else {
-                has_selection
+        #[template_callback]
+        fn mode_to_files_list(&self, mode: FileSelectorMode, files: Vec<String>) -> bool {
+            mode == FileSelectorMode::SaveFiles && !files.is_empty()
+        }
+
+        #[template_callback]
+        fn mode_to_can_create_folder(&self, mode: FileSelectorMode) -> bool {
+            match mode {
+                FileSelectorMode::OpenFile => false,
+                FileSelectorMode::SaveFile => true,
+                FileSelectorMode::SaveFiles => true,
             }
         }
 
@@ -564,6 +972,9 @@ impl FileSelector {
         );
         self.set_sort_mode(mode_name, reversed);
 
+        // `DirView` picks up changes via its settings binding
+        actions.add_action(&settings.create_action("view-mode"));
+
         let pos = self.imp().current_filter.get().to_string();
         stateful_action!(
             actions,
@@ -584,6 +995,30 @@ impl FileSelector {
             )
         );
 
+        let add_bookmark_action = gio::SimpleAction::new("add-bookmark", None);
+        add_bookmark_action.connect_activate(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| {
+                if let Some(folder) = this.current_folder() {
+                    this.imp().places_box.add_bookmark(&folder);
+                }
+            }
+        ));
+        actions.add_action(&add_bookmark_action);
+
+        let new_folder_action = gio::SimpleAction::new("new-folder", None);
+        new_folder_action.connect_activate(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| {
+                this.new_folder();
+            }
+        ));
+        actions.add_action(&new_folder_action);
+        *self.imp().new_folder_action.borrow_mut() = Some(new_folder_action);
+        self.update_new_folder_action();
+
         self.upcast_ref::<gtk::Widget>()
             .insert_action_group("file-selector", Some(&actions));
 
@@ -601,11 +1036,15 @@ impl FileSelector {
     }
 
     fn confirm_overwrite(&self, file: gio::File) {
-        let basename = file.basename().unwrap();
-        let dirname = file.parent().unwrap().path().unwrap();
+        let basename = util::file_display_name(&file);
+        let dirname = file
+            .parent()
+            .and_then(|parent| parent.path())
+            .map(|path| glib::filename_display_name(path).to_string())
+            .unwrap_or_default();
         let body = gettextrs::gettext("Overwrite existing file {} in {}?")
-            .replacen("{}", basename.to_str().unwrap(), 1)
-            .replacen("{}", dirname.to_str().unwrap(), 1);
+            .replacen("{}", &basename, 1)
+            .replacen("{}", &dirname, 1);
 
         let dialog = adw::AlertDialog::builder()
             .title(&gettextrs::gettext("Replace existing file?"))
@@ -633,19 +1072,285 @@ impl FileSelector {
         );
     }
 
+    fn edit_location(&self, text: Option<&str>) {
+        // In the sidebar layout the places are always visible
+        if self.imp().split_view.is_collapsed() {
+            self.imp().bottom_sheet.set_open(true);
+        } else {
+            self.imp().split_view.set_show_sidebar(true);
+        }
+        self.imp().places_box.edit_location(text);
+    }
+
+    fn update_new_folder_action(&self) {
+        let binding = self.imp().new_folder_action.borrow();
+        let Some(action) = binding.as_ref() else {
+            return;
+        };
+
+        let enabled = self.mode() != FileSelectorMode::OpenFile
+            && util::is_valid_folder(&self.current_folder());
+        action.set_enabled(enabled);
+    }
+
+    fn new_folder(&self) {
+        let Some(folder) = self.current_folder() else {
+            return;
+        };
+
+        self.show_new_folder_dialog(folder, "", None);
+    }
+
+    // Ask for the name of a new folder in `folder`. `name` and `error` are
+    // used to ask again when creating the folder failed.
+    fn show_new_folder_dialog(&self, folder: gio::File, name: &str, error: Option<String>) {
+        let entry = gtk::Entry::builder().activates_default(true).build();
+        let error_label = gtk::Label::builder()
+            .wrap(true)
+            .xalign(0.0)
+            .visible(false)
+            .css_classes(["caption", "error"])
+            .build();
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        content.append(&entry);
+        content.append(&error_label);
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettextrs::gettext("New Folder"))
+            .extra_child(&content)
+            .close_response("cancel")
+            .default_response("create")
+            .build();
+
+        dialog.add_response("cancel", &gettextrs::gettext("Cancel"));
+        dialog.add_response("create", &gettextrs::gettext("_Create"));
+        dialog.set_response_appearance("create", adw::ResponseAppearance::Suggested);
+        dialog.set_response_enabled("create", false);
+
+        entry.connect_changed(clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            error_label,
+            move |entry| {
+                // Existing names are reported when creating the folder fails
+                let error = match util::validate_filename(&entry.text()) {
+                    Err(util::FilenameError::Empty) => Some(None),
+                    Err(err) => Some(Some(err.message())),
+                    Ok(()) => None,
+                };
+
+                // Empty names are invalid but don't warrant an error message
+                let message = error.clone().flatten();
+                error_label.set_label(message.as_deref().unwrap_or_default());
+                error_label.set_visible(message.is_some());
+                dialog.set_response_enabled("create", error.is_none());
+            }
+        ));
+        entry.set_text(name);
+
+        if let Some(error) = error {
+            error_label.set_label(&error);
+            error_label.set_visible(true);
+            dialog.set_response_enabled("create", false);
+        }
+
+        dialog.choose(
+            self.upcast_ref::<gtk::Widget>(),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                entry,
+                move |response| {
+                    if response != "create" {
+                        return;
+                    }
+
+                    this.create_folder(folder, entry.text().to_string());
+                }
+            ),
+        );
+    }
+
+    fn create_folder(&self, parent: gio::File, name: String) {
+        let folder = parent.child(&name);
+
+        folder.make_directory_async(
+            glib::Priority::DEFAULT,
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[strong]
+                folder,
+                move |result| {
+                    let uri = folder.uri();
+
+                    if let Err(err) = result {
+                        if err.matches(gio::IOErrorEnum::Exists) {
+                            // Let the user pick another name
+                            this.show_new_folder_dialog(
+                                parent,
+                                &name,
+                                Some(gettextrs::gettext(
+                                    "A file or folder with that name already exists",
+                                )),
+                            );
+                            return;
+                        }
+
+                        glib::g_warning!(LOG_DOMAIN, "Failed to create {uri:#?}: {err}");
+                        this.show_error(&gettextrs::gettext("Failed to create folder"), &err);
+                        return;
+                    }
+
+                    glib::g_debug!(LOG_DOMAIN, "Created {uri:#?}");
+                    this.set_current_folder(folder);
+                }
+            ),
+        );
+    }
+
+    fn show_error(&self, heading: &str, err: &glib::Error) {
+        let dialog = adw::AlertDialog::builder()
+            .heading(heading)
+            .body(err.message())
+            .close_response("close")
+            .default_response("close")
+            .build();
+
+        dialog.add_response("close", &gettextrs::gettext("_Close"));
+        dialog.present(Some(self));
+    }
+
     pub fn selected(&self) -> Option<Vec<String>> {
         let items = self.imp().dir_view.get().selected();
 
+        if self.mode() == FileSelectorMode::SaveFiles && !self.files().is_empty() {
+            // No valid folder selected
+            if items.is_none() {
+                return None;
+            }
+
+            let folder = self.current_folder()?;
+            let uris = self
+                .imp()
+                .save_items
+                .borrow()
+                .iter()
+                .filter(|item| !item.skipped())
+                .map(|item| item.file(&folder).uri().to_string())
+                .collect::<Vec<_>>();
+
+            // Everything got skipped, nothing to save
+            if uris.is_empty() {
+                return None;
+            }
+
+            return Some(uris);
+        }
+
         if self.mode() == FileSelectorMode::SaveFile {
-            let path = self.current_folder().unwrap().path().unwrap();
-            let file = gio::File::for_path(path.join(self.filename()));
+            if util::validate_filename(&self.filename()).is_err() {
+                return None;
+            }
 
+            let folder = self.current_folder()?;
+
+            // Prefer the selected file so names that aren't valid UTF-8 are kept
+            if let Some(uri) = items.as_ref().and_then(|uris| uris.first()) {
+                let file = gio::File::for_uri(uri);
+                let in_folder = file.parent().is_some_and(|parent| parent.equal(&folder));
+                if in_folder && util::file_display_name(&file) == self.filename() {
+                    return Some(vec![uri.clone()]);
+                }
+            }
+
+            let file = folder.child(self.filename());
             Some(vec![file.uri().to_string()])
         } else {
             items
         }
     }
 
+    // Like `set_files` but keeps names that aren't valid UTF-8 intact
+    pub fn set_files_from_paths(&self, names: &[PathBuf]) {
+        let display_names = names
+            .iter()
+            .map(|name| glib::filename_display_name(name).to_string())
+            .collect::<Vec<String>>();
+        self.set_files(display_names);
+
+        for (item, name) in self.imp().save_items.borrow().iter().zip(names) {
+            item.set_raw_name(name);
+        }
+    }
+
+    // Select `file` in the current folder once it's loaded
+    pub fn select_file(&self, file: &gio::File) {
+        self.imp().dir_view.select_file(file);
+    }
+
+    // Set `filters` and `current-filter` from the portal's `a(sa(us))` and `(sa(us))` formats
+    pub fn set_portal_filters(
+        &self,
+        filters: Option<&glib::Variant>,
+        current_filter: Option<&glib::Variant>,
+    ) {
+        let filters_type = glib::VariantTy::new("a(sa(us))").unwrap();
+        let filter_type = glib::VariantTy::new("(sa(us))").unwrap();
+
+        let store = gio::ListStore::with_type(gtk::FileFilter::static_type());
+        if let Some(filters) = filters {
+            if filters.is_type(filters_type) {
+                for filter in filters.iter() {
+                    store.append(&gtk::FileFilter::from_gvariant(&filter));
+                }
+            } else {
+                glib::g_warning!(LOG_DOMAIN, "Invalid filters format {}", filters.type_());
+            }
+        }
+
+        let mut pos = gtk::INVALID_LIST_POSITION;
+        if let Some(current) = current_filter {
+            if current.is_type(filter_type) {
+                pos = store
+                    .iter::<gtk::FileFilter>()
+                    .position(|filter| filter.is_ok_and(|filter| filter.to_gvariant() == *current))
+                    .map(|pos| pos as u32)
+                    .unwrap_or_else(|| {
+                        // Not in the list, the portal spec allows to use it nevertheless
+                        store.append(&gtk::FileFilter::from_gvariant(current));
+                        store.n_items() - 1
+                    });
+            } else {
+                glib::g_warning!(LOG_DOMAIN, "Invalid filter format {}", current.type_());
+            }
+        }
+
+        // Reset first so the type filter gets reapplied even if `pos` didn't change
+        self.set_current_filter(gtk::INVALID_LIST_POSITION);
+        self.set_filters(store.upcast_ref::<gio::ListModel>());
+        self.set_current_filter(pos);
+    }
+
+    // The currently selected filter in the portal's `(sa(us))` format
+    pub fn portal_current_filter(&self) -> Option<glib::Variant> {
+        let filters = self.filters()?;
+        let filter = filters
+            .item(self.current_filter())
+            .and_downcast::<gtk::FileFilter>()?;
+
+        Some(filter.to_gvariant())
+    }
+
+    // Dismiss the selector as if the user cancelled it
+    pub fn cancel(&self) {
+        self.imp().send_done(false, true);
+    }
+
     pub fn set_current_directory(&self, directory: String) {
         let file = gio::File::for_path(directory);
 
@@ -690,12 +1395,24 @@ pub unsafe extern "C" fn pfs_file_selector_set_accept_label(
     obj.set_accept_label(label.to_string());
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn pfs_file_selector_set_app_id(
+    fs: *mut PfsFileSelector,
+    app_id: *const std::ffi::c_char,
+) {
+    let obj = FileSelector::from_glib_ptr_borrow(&fs);
+    let app_id: Borrowed<glib::GString> = from_glib_borrow(app_id);
+
+    obj.set_app_id(app_id.to_string());
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn pfs_file_selector_get_selected(
     fs: *mut PfsFileSelector,
 ) -> *const *mut std::ffi::c_char {
     let obj = FileSelector::from_glib_ptr_borrow(&fs);
-    let strv: glib::StrV = obj.selected().unwrap().into();
+    // Nothing to save when the user skipped all files
+    let strv: glib::StrV = obj.selected().unwrap_or_default().into();
 
     strv.into_raw()
 }
@@ -708,6 +1425,16 @@ pub unsafe extern "C" fn pfs_file_selector_set_mode(fs: *mut PfsFileSelector, mo
     obj.set_mode(mode);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn pfs_file_selector_set_multiple(
+    fs: *mut PfsFileSelector,
+    multiple: glib::ffi::gboolean,
+) {
+    let obj = FileSelector::from_glib_ptr_borrow(&fs);
+
+    obj.set_multiple(from_glib(multiple));
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn pfs_file_selector_set_filename(
     fs: *mut PfsFileSelector,
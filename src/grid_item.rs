@@ -8,11 +8,15 @@
 
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use glib_macros::Properties;
+use glib_macros::{clone, Properties};
 use gtk::{gio, glib, CompositeTemplate};
 use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 
-use crate::dir_view::ThumbnailMode;
+use crate::config::LOG_DOMAIN;
+use crate::dir_view::{ThumbnailMode, PARENT_FOLDER_ATTRIBUTE};
+use crate::texture_cache::TextureCache;
+use crate::thumbnailer::{ThumbnailSize, Thumbnailer};
 
 mod imp {
     use super::*;
@@ -27,14 +31,26 @@ mod imp {
         #[template_child]
         pub label: TemplateChild<gtk::Label>,
 
+        #[template_child]
+        pub subtitle: TemplateChild<gtk::Label>,
+
         #[property(get, set = Self::set_fileinfo)]
         fileinfo: RefCell<Option<gio::FileInfo>>,
 
-        #[property(get, set)]
+        #[property(get, set = Self::set_icon_size)]
         icon_size: Cell<u32>,
 
         #[property(get, set = Self::set_thumbnail_mode, builder(ThumbnailMode::default()))]
         pub thumbnail_mode: RefCell<ThumbnailMode>,
+
+        #[property(get, set = Self::set_thumbnail_max_size)]
+        thumbnail_max_size: Cell<u64>,
+
+        // Cancels the thumbnail currently being loaded
+        load_cancellable: RefCell<Option<gio::Cancellable>>,
+
+        // Cancels the pending thumbnail request
+        thumbnail_cancellable: RefCell<Option<gio::Cancellable>>,
     }
 
     #[glib::object_subclass]
@@ -55,32 +71,155 @@ mod imp {
 
     impl GridItem {
         fn update_image(&self) {
-            let mut have_thumbnail = false;
+            // A recycled item must not end up with the previous file's image
+            self.cancel();
 
             let borrowed = self.fileinfo.borrow();
             let Some(info) = borrowed.as_ref() else {
                 return;
             };
-            if *self.thumbnail_mode.borrow() != ThumbnailMode::Never {
-                if let Some(path) = info.attribute_byte_string("thumbnail::path") {
-                    let valid = info.boolean("thumbnail::is-valid");
-                    if valid {
-                        self.icon.get().set_from_file(Some(path));
-                        have_thumbnail = true;
+
+            if let Some(icon) = info.icon() {
+                self.icon.get().set_from_gicon(&icon)
+            };
+
+            let size = self.thumbnail_size();
+            if self.wants_thumbnail(info) {
+                if let Some(path) = info.attribute_byte_string(&size.attribute("path")) {
+                    if info.boolean(&size.attribute("is-valid")) {
+                        self.load_thumbnail(path.into(), info.attribute_uint64("time::modified"));
+                        return;
+                    }
+                }
+            }
+
+            self.request_thumbnail(info, size);
+        }
+
+        // Stop loading the image and creating the thumbnail
+        pub(super) fn cancel(&self) {
+            self.cancel_load();
+            if let Some(cancellable) = self.thumbnail_cancellable.take() {
+                cancellable.cancel();
+            }
+        }
+
+        fn cancel_load(&self) {
+            if let Some(cancellable) = self.load_cancellable.take() {
+                cancellable.cancel();
+            }
+        }
+
+        // Show the thumbnail at `path` once it's loaded, `mtime` is the
+        // modification time of the file it was made for
+        fn load_thumbnail(&self, path: PathBuf, mtime: u64) {
+            self.cancel_load();
+
+            let cache = TextureCache::get();
+            if let Some(texture) = cache.lookup(&path, mtime) {
+                self.icon.get().set_from_paintable(Some(&texture));
+                return;
+            }
+
+            let cancellable = gio::Cancellable::new();
+            self.load_cancellable.replace(Some(cancellable.clone()));
+            glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let result = cache.load(&path, mtime, &cancellable).await;
+                    if cancellable.is_cancelled() {
+                        return;
                     }
+
+                    match result {
+                        Ok(texture) => this.icon.get().set_from_paintable(Some(&texture)),
+                        Err(err) => {
+                            glib::g_warning!(LOG_DOMAIN, "Failed to load {path:#?}: {err}")
+                        }
+                    }
+                    this.load_cancellable.replace(None);
                 }
+            ));
+        }
+
+        // The thumbnail flavour matching the icon size on this display
+        fn thumbnail_size(&self) -> ThumbnailSize {
+            let pixels = self.icon_size.get() * self.obj().scale_factor().max(1) as u32;
+            ThumbnailSize::for_icon_size(pixels)
+        }
+
+        fn wants_thumbnail(&self, info: &gio::FileInfo) -> bool {
+            let Some(file) = info
+                .attribute_object("standard::file")
+                .and_downcast::<gio::File>()
+            else {
+                return false;
             };
 
-            if !have_thumbnail {
-                if let Some(icon) = info.icon() {
-                    self.icon.get().set_from_gicon(&icon)
-                };
+            self.thumbnail_mode.borrow().allows(
+                &file,
+                info.attribute_uint64("standard::size"),
+                self.thumbnail_max_size.get(),
+            )
+        }
+
+        // Have a thumbnail generated, the image gets updated once it's ready
+        fn request_thumbnail(&self, info: &gio::FileInfo, size: ThumbnailSize) {
+            if !self.wants_thumbnail(info) {
+                return;
+            }
+
+            // Other thumbnailers already failed on this one
+            if info.boolean(&size.attribute("failed")) {
+                return;
             }
+
+            let Some(file) = info
+                .attribute_object("standard::file")
+                .and_downcast::<gio::File>()
+            else {
+                return;
+            };
+            let uri = file.uri();
+            let mtime = info.attribute_uint64("time::modified");
+            let cancellable = Thumbnailer::get().request(
+                info,
+                size,
+                clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |path| {
+                        let Some(path) = path else {
+                            return;
+                        };
+
+                        // The item might show another file by now
+                        let current = this
+                            .fileinfo
+                            .borrow()
+                            .as_ref()
+                            .and_then(|info| info.attribute_object("standard::file"))
+                            .and_downcast::<gio::File>();
+                        if current.is_some_and(|current| current.uri() == uri) {
+                            this.load_thumbnail(path, mtime);
+                        }
+                    }
+                ),
+            );
+            self.thumbnail_cancellable.replace(cancellable);
         }
 
         fn set_fileinfo(&self, info: gio::FileInfo) {
             self.label.get().set_label(&info.display_name());
 
+            // Search results show the folder they're in
+            let parent_folder = info.attribute_string(PARENT_FOLDER_ATTRIBUTE);
+            self.subtitle.get().set_visible(parent_folder.is_some());
+            self.subtitle
+                .get()
+                .set_label(parent_folder.as_ref().map_or("", |folder| folder.as_str()));
+
             *self.fileinfo.borrow_mut() = Some(info);
             self.update_image();
         }
@@ -93,6 +232,25 @@ mod imp {
             self.thumbnail_mode.replace(mode);
             self.update_image();
         }
+
+        fn set_thumbnail_max_size(&self, max_size: u64) {
+            if self.thumbnail_max_size.get() == max_size {
+                return;
+            }
+
+            self.thumbnail_max_size.set(max_size);
+            self.update_image();
+        }
+
+        // Larger icons might need another thumbnail flavour
+        fn set_icon_size(&self, icon_size: u32) {
+            if self.icon_size.get() == icon_size {
+                return;
+            }
+
+            self.icon_size.set(icon_size);
+            self.update_image();
+        }
     }
 
     #[glib::derived_properties]
@@ -123,4 +281,9 @@ impl GridItem {
     pub fn new() -> Self {
         Self::default()
     }
+
+    // The item isn't shown anymore, don't bother with its image
+    pub fn unbind(&self) {
+        self.imp().cancel();
+    }
 }
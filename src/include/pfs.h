@@ -29,9 +29,13 @@ void             pfs_file_selector_set_current_directory (PfsFileSelector      *
                                                           const char           *directory);
 void             pfs_file_selector_set_accept_label      (PfsFileSelector      *self,
                                                           const char           *accept_label);
+void             pfs_file_selector_set_app_id            (PfsFileSelector      *self,
+                                                          const char           *app_id);
 GStrv            pfs_file_selector_get_selected          (PfsFileSelector      *self);
 void             pfs_file_selector_set_mode              (PfsFileSelector      *self,
                                                           PfsFileSelectorMode   mode);
 void             pfs_file_selector_set_filename          (PfsFileSelector      *self,
                                                           const char           *suggested);
+void             pfs_file_selector_set_multiple          (PfsFileSelector      *self,
+                                                          gboolean              multiple);
 G_END_DECLS
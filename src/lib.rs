@@ -9,6 +9,8 @@
 pub mod file_selector;
 pub mod init;
 
+mod app_state;
+mod bookmarks;
 mod config;
 mod dir_stack;
 mod dir_view;
@@ -16,5 +18,8 @@ mod grid_item;
 mod path_bar;
 mod places_box;
 mod places_item;
+mod save_item;
+mod texture_cache;
+mod thumbnailer;
 #[macro_use]
 mod util;
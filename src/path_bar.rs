@@ -10,11 +10,14 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::Signal;
 use glib_macros::{clone, Properties};
-use gtk::{gio, glib, CompositeTemplate};
-use std::cell::RefCell;
+use gtk::{gdk, gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
 use std::sync::OnceLock;
 
-use crate::config::LOG_DOMAIN;
+use crate::{config::LOG_DOMAIN, util};
+
+// How many folder entries to fetch at once when completing
+const COMPLETION_BATCH_SIZE: i32 = 256;
 
 mod imp {
     use super::*;
@@ -29,9 +32,37 @@ mod imp {
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
 
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+
+        #[template_child]
+        pub location_entry: TemplateChild<gtk::Entry>,
+
+        #[template_child]
+        pub overflow_button: TemplateChild<gtk::MenuButton>,
+
+        #[template_child]
+        pub overflow_popover: TemplateChild<gtk::Popover>,
+
+        #[template_child]
+        pub overflow_box: TemplateChild<gtk::Box>,
+
         // The current folder
         #[property(get, set = Self::set_folder)]
         folder: RefCell<Option<gio::File>>,
+
+        // Whether the location is shown as editable text
+        #[property(get, set = Self::set_editing, explicit_notify)]
+        editing: Cell<bool>,
+
+        // The crumbs from the first one to the deepest known folder
+        pub(super) crumbs: RefCell<Vec<(gio::File, gtk::Button)>>,
+        // Index of the current folder in `crumbs`
+        pub(super) current: Cell<usize>,
+        // Number of crumbs after the first one moved to the overflow popover
+        pub(super) collapsed: Cell<usize>,
+        // Cancels completing or entering the typed location
+        pub(super) location_cancellable: RefCell<Option<gio::Cancellable>>,
     }
 
     #[glib::object_subclass]
@@ -51,52 +82,230 @@ mod imp {
     }
 
     impl PathBar {
+        fn set_editing(&self, editing: bool) {
+            if self.editing.get() == editing {
+                return;
+            }
+
+            self.editing.set(editing);
+            if editing {
+                let location = self.folder_to_location();
+                self.location_entry.set_text(&location);
+                self.stack.set_visible_child_name("location");
+                self.location_entry.grab_focus();
+            } else {
+                self.stack.set_visible_child_name("crumbs");
+            }
+
+            self.obj().notify_editing();
+        }
+
+        // The text to prefill the location entry with
+        fn folder_to_location(&self) -> String {
+            let Some(folder) = self.folder.borrow().clone() else {
+                return String::new();
+            };
+
+            // Use the URI for names that aren't valid UTF-8 so the
+            // location still resolves to the same folder
+            match folder.path().as_deref().map(|path| path.to_str()) {
+                Some(Some("/")) => "/".to_string(),
+                Some(Some(path)) => format!("{path}/"),
+                _ => folder.uri().to_string(),
+            }
+        }
+
         fn set_folder(&self, folder: Option<gio::File>) {
             let Some(folder) = folder else { return };
 
-            while let Some(child) = self.path_box.first_child() {
-                self.path_box.remove(&child);
+            self.obj().set_editing(false);
+            *self.folder.borrow_mut() = Some(folder.clone());
+
+            // Keep the crumbs leading into the forward history
+            let index = self
+                .crumbs
+                .borrow()
+                .iter()
+                .position(|(crumb, _)| crumb.equal(&folder));
+            match index {
+                Some(index) => self.set_current(index),
+                None => self.rebuild_crumbs(&folder),
+            }
+        }
+
+        fn rebuild_crumbs(&self, folder: &gio::File) {
+            for (_, button) in self.crumbs.take() {
+                self.path_box.remove(&button);
             }
 
-            let Some(path) = folder.path() else {
-                self.path_box.set_visible(false);
+            // Walk up the hierarchy so this works for any gio location,
+            // folders below $HOME start at the home crumb
+            let home = gio::File::for_path(glib::home_dir());
+            let mut folders = vec![folder.clone()];
+            loop {
+                let last = folders.last().unwrap();
+                if last.equal(&home) {
+                    break;
+                }
+                let Some(parent) = last.parent() else {
+                    break;
+                };
+                folders.push(parent);
+            }
+
+            let crumbs = folders
+                .into_iter()
+                .rev()
+                .map(|folder| {
+                    let button = self.crumb_button(&folder);
+                    self.path_box.append(&button);
+                    (folder, button)
+                })
+                .collect::<Vec<_>>();
+
+            // The overflow button sits between the first crumb and the rest
+            let first = crumbs
+                .first()
+                .map(|(_, button)| button.upcast_ref::<gtk::Widget>());
+            self.path_box
+                .reorder_child_after(&self.overflow_button.get(), first);
+
+            let last = crumbs.len() - 1;
+            *self.crumbs.borrow_mut() = crumbs;
+            self.set_current(last);
+        }
+
+        fn set_current(&self, current: usize) {
+            self.current.set(current);
+
+            for (index, (_, button)) in self.crumbs.borrow().iter().enumerate() {
+                button.set_visible(true);
+                if index == current {
+                    button.add_css_class("current");
+                } else {
+                    button.remove_css_class("current");
+                }
+                // Crumbs in the forward history
+                if index > current {
+                    button.add_css_class("dim-label");
+                } else {
+                    button.remove_css_class("dim-label");
+                }
+            }
+
+            self.collapsed.set(0);
+            self.overflow_button.set_visible(false);
+            self.queue_relayout();
+        }
+
+        // Collapse and scroll once the size allocation settled
+        fn queue_relayout(&self) {
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move || {
+                    this.update_collapsed();
+                    this.scroll_to_current();
+                }
+            ));
+        }
+
+        // Move crumbs into the overflow popover until the rest fits
+        fn update_collapsed(&self) {
+            let available = self.scrolled_window.width();
+            // Not allocated yet
+            if available == 0 {
                 return;
-            };
+            }
+
+            let needed = || self.path_box.measure(gtk::Orientation::Horizontal, -1).1;
+            let crumbs = self.crumbs.borrow();
+            let current = self.current.get();
+
+            // Only the crumbs between the first and the current one collapse
+            while needed() > available && self.collapsed.get() + 1 < current {
+                let collapsed = self.collapsed.get() + 1;
+                crumbs[collapsed].1.set_visible(false);
+                self.collapsed.set(collapsed);
+                self.overflow_button.set_visible(true);
+            }
+
+            while self.collapsed.get() > 0 {
+                let collapsed = self.collapsed.get();
+                crumbs[collapsed].1.set_visible(true);
+                self.overflow_button.set_visible(collapsed > 1);
+
+                if needed() > available {
+                    crumbs[collapsed].1.set_visible(false);
+                    self.overflow_button.set_visible(true);
+                    break;
+                }
+                self.collapsed.set(collapsed - 1);
+            }
+
+            self.overflow_button.set_visible(self.collapsed.get() > 0);
 
-            self.path_box.set_visible(true);
-            for part in &path {
-                let button = gtk::Button::new();
-                button.set_label(part.to_str().unwrap());
-                self.path_box.append(&button);
+            while let Some(child) = self.overflow_box.first_child() {
+                self.overflow_box.remove(&child);
+            }
+            for (folder, _) in crumbs.iter().skip(1).take(self.collapsed.get()) {
+                let button = self.crumb_button(folder);
+                button.add_css_class("flat");
                 button.connect_clicked(clone!(
                     #[weak(rename_to = this)]
                     self,
-                    move |clicked_button| {
-                        let mut child = this.path_box.first_child().unwrap();
-
-                        let mut pathbuf = std::path::PathBuf::new();
-                        loop {
-                            let button = child.downcast_ref::<gtk::Button>().unwrap();
-                            pathbuf.push(button.label().unwrap());
+                    move |_| this.overflow_popover.popdown()
+                ));
+                self.overflow_box.append(&button);
+            }
+        }
 
-                            if child == *clicked_button.upcast_ref::<gtk::Widget>() {
-                                break;
-                            }
+        // Scroll such that the current crumb is visible
+        fn scroll_to_current(&self) {
+            let crumbs = self.crumbs.borrow();
+            let Some((_, button)) = crumbs.get(self.current.get()) else {
+                return;
+            };
+            let Some(bounds) = button.compute_bounds(&self.path_box.get()) else {
+                return;
+            };
 
-                            child = match child.next_sibling() {
-                                Some(child) => child,
-                                None => break,
-                            };
-                        }
+            let adjustment = self.scrolled_window.hadjustment();
+            let left = bounds.x() as f64;
+            let right = (bounds.x() + bounds.width()) as f64;
+            if right > adjustment.value() + adjustment.page_size() {
+                adjustment.set_value(right - adjustment.page_size());
+            } else if left < adjustment.value() {
+                adjustment.set_value(left);
+            }
+        }
 
-                        let file = gio::File::for_path(pathbuf);
-                        let uri = file.uri();
-                        glib::g_debug!(LOG_DOMAIN, "Selected path {uri:#?}");
+        fn crumb_button(&self, folder: &gio::File) -> gtk::Button {
+            let content = gtk::Box::new(gtk::Orientation::Horizontal, 6);
 
-                        this.obj().emit_by_name::<()>("new-uri", &[&uri]);
-                    }
-                ));
+            let icon_name = util::folder_to_icon_name(folder.clone());
+            if icon_name != "folder-symbolic" {
+                content.append(&gtk::Image::from_icon_name(icon_name));
             }
+            content.append(&gtk::Label::new(Some(&util::folder_to_name(
+                folder.clone(),
+            ))));
+
+            let button = gtk::Button::builder().child(&content).build();
+            button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[strong]
+                folder,
+                move |_| {
+                    let uri = folder.uri();
+                    glib::g_debug!(LOG_DOMAIN, "Selected path {uri:#?}");
+
+                    this.obj().emit_by_name::<()>("new-uri", &[&uri]);
+                }
+            ));
+
+            button
         }
     }
 
@@ -104,14 +313,49 @@ mod imp {
     impl ObjectImpl for PathBar {
         fn constructed(&self) {
             self.parent_constructed();
+
+            let controller = gtk::EventControllerKey::new();
+            controller.connect_key_pressed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |_, key, _, _| {
+                    match key {
+                        gdk::Key::Escape => this.obj().set_editing(false),
+                        gdk::Key::Tab => this.obj().complete_location(),
+                        _ => return glib::Propagation::Proceed,
+                    }
+                    glib::Propagation::Stop
+                }
+            ));
+            self.location_entry.add_controller(controller);
+
+            // Re-layout the crumbs whenever the available width changes
+            let adjustment = self.scrolled_window.hadjustment();
+            adjustment.connect_page_size_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| this.queue_relayout()
+            ));
+            adjustment.connect_upper_notify(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| this.scroll_to_current()
+            ));
         }
 
         fn signals() -> &'static [Signal] {
             static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
             SIGNALS.get_or_init(|| {
-                vec![Signal::builder("new-uri")
-                    .param_types([String::static_type()])
-                    .build()]
+                vec![
+                    Signal::builder("new-uri")
+                        .param_types([String::static_type()])
+                        .build(),
+                    Signal::builder("select-uri")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
             })
         }
     }
@@ -136,4 +380,247 @@ impl PathBar {
     pub fn new() -> Self {
         Self::default()
     }
+
+    // Switch to the location entry, optionally replacing its text
+    pub fn edit_location(&self, text: Option<&str>) {
+        self.set_editing(true);
+
+        let Some(text) = text else {
+            return;
+        };
+
+        let entry = self.imp().location_entry.get();
+        entry.set_text(text);
+        entry.set_position(-1);
+    }
+
+    // Turn the text of the location entry into a file
+    fn location_to_file(&self, text: &str) -> Option<gio::File> {
+        if text.is_empty() {
+            return None;
+        }
+
+        if text == "~" {
+            return Some(gio::File::for_path(glib::home_dir()));
+        }
+
+        if let Some(rest) = text.strip_prefix("~/") {
+            return Some(gio::File::for_path(glib::home_dir().join(rest)));
+        }
+
+        if text.starts_with('/') {
+            return Some(gio::File::for_path(text));
+        }
+
+        if glib::Uri::peek_scheme(text).is_some() {
+            return Some(gio::File::for_uri(text));
+        }
+
+        self.folder()
+            .map(|folder| folder.resolve_relative_path(text))
+    }
+
+    // Cancel pending lookups of the typed location, returns a cancellable
+    // for the next one
+    fn reset_location_cancellable(&self) -> gio::Cancellable {
+        let cancellable = gio::Cancellable::new();
+        if let Some(old) = self
+            .imp()
+            .location_cancellable
+            .replace(Some(cancellable.clone()))
+        {
+            old.cancel();
+        }
+        cancellable
+    }
+
+    fn complete_location(&self) {
+        let entry = self.imp().location_entry.get();
+        let text = entry.text().to_string();
+
+        let (dir, prefix) = match text.rfind('/') {
+            Some(index) => text.split_at(index + 1),
+            None => ("", text.as_str()),
+        };
+
+        let folder = if dir.is_empty() {
+            self.folder()
+        } else {
+            self.location_to_file(dir)
+        };
+        let Some(folder) = folder else {
+            entry.error_bell();
+            return;
+        };
+
+        let (dir, prefix) = (dir.to_string(), prefix.to_string());
+        let cancellable = self.reset_location_cancellable();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let names = gio::CancellableFuture::new(Self::folder_names(folder), cancellable);
+                match names.await {
+                    Ok(Ok(names)) => this.complete_from(&names, &dir, &prefix),
+                    Ok(Err(err)) => {
+                        glib::g_debug!(LOG_DOMAIN, "Can't complete {prefix:#?}: {err}");
+                        this.imp().location_entry.error_bell();
+                    }
+                    // The user typed on
+                    Err(_) => {}
+                }
+            }
+        ));
+    }
+
+    // Names of the subfolders of `folder`. Names that aren't valid UTF-8
+    // can't be typed so they're skipped.
+    async fn folder_names(folder: gio::File) -> Result<Vec<String>, glib::Error> {
+        let enumerator = folder
+            .enumerate_children_future(
+                "standard::name,standard::type",
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::DEFAULT,
+            )
+            .await?;
+
+        let mut names = Vec::new();
+        loop {
+            let infos = enumerator
+                .next_files_future(COMPLETION_BATCH_SIZE, glib::Priority::DEFAULT)
+                .await?;
+            if infos.is_empty() {
+                return Ok(names);
+            }
+
+            names.extend(
+                infos
+                    .iter()
+                    .filter(|info| info.file_type() == gio::FileType::Directory)
+                    .filter_map(|info| info.name().to_str().map(str::to_string)),
+            );
+        }
+    }
+
+    fn complete_from(&self, names: &[String], dir: &str, prefix: &str) {
+        let entry = self.imp().location_entry.get();
+
+        // Only complete hidden folders when asked for
+        let show_hidden = prefix.starts_with('.');
+        let matches = names
+            .iter()
+            .filter(|name| name.starts_with(prefix) && (show_hidden || !name.starts_with('.')))
+            .collect::<Vec<&String>>();
+
+        let completion = match matches.as_slice() {
+            [] => None,
+            [name] => Some(format!("{name}/")),
+            [first, rest @ ..] => {
+                let mut common = first.to_string();
+                for name in rest {
+                    let len = common
+                        .char_indices()
+                        .zip(name.chars())
+                        .find(|((_, a), b)| a != b)
+                        .map(|((index, _), _)| index)
+                        .unwrap_or_else(|| common.len().min(name.len()));
+                    common.truncate(len);
+                }
+                Some(common)
+            }
+        };
+
+        match completion {
+            Some(completion) if completion.len() > prefix.len() => {
+                entry.set_text(&format!("{dir}{completion}"));
+                entry.set_position(-1);
+            }
+            _ => entry.error_bell(),
+        }
+    }
+
+    #[template_callback]
+    fn on_location_changed(&self) {
+        self.imp().location_entry.remove_css_class("error");
+
+        // Lookups of what was typed before are stale now
+        if let Some(cancellable) = self.imp().location_cancellable.take() {
+            cancellable.cancel();
+        }
+    }
+
+    #[template_callback]
+    fn on_location_activate(&self) {
+        let entry = self.imp().location_entry.get();
+        let text = entry.text();
+
+        let Some(file) = self.location_to_file(&text) else {
+            return;
+        };
+
+        let cancellable = self.reset_location_cancellable();
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let result = gio::CancellableFuture::new(this.enter_location(&file), cancellable);
+                match result.await {
+                    Ok(Ok(())) => this.set_editing(false),
+                    Ok(Err(err)) => {
+                        glib::g_debug!(LOG_DOMAIN, "Can't enter {:#?}: {err}", file.uri());
+                        let entry = this.imp().location_entry.get();
+                        entry.add_css_class("error");
+                        entry.error_bell();
+                    }
+                    // The user typed on
+                    Err(_) => {}
+                }
+            }
+        ));
+    }
+
+    // Open `file` if it's a folder or select it otherwise. Remote locations
+    // get mounted first.
+    async fn enter_location(&self, file: &gio::File) -> Result<(), glib::Error> {
+        let uri = file.uri();
+
+        let info = match file
+            .query_info_future(
+                "standard::type",
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::DEFAULT,
+            )
+            .await
+        {
+            Ok(info) => info,
+            Err(err) if err.matches(gio::IOErrorEnum::NotMounted) => {
+                glib::g_debug!(LOG_DOMAIN, "Mounting {uri:#?}");
+                let window = self.root().and_downcast::<gtk::Window>();
+                let operation = gtk::MountOperation::new(window.as_ref());
+                file.mount_enclosing_volume_future(gio::MountMountFlags::NONE, Some(&operation))
+                    .await?;
+
+                file.query_info_future(
+                    "standard::type",
+                    gio::FileQueryInfoFlags::NONE,
+                    glib::Priority::DEFAULT,
+                )
+                .await?
+            }
+            Err(err) => return Err(err),
+        };
+
+        match info.file_type() {
+            gio::FileType::Directory | gio::FileType::Mountable => {
+                glib::g_debug!(LOG_DOMAIN, "Entered folder {uri:#?}");
+                self.emit_by_name::<()>("new-uri", &[&uri]);
+            }
+            _ => {
+                glib::g_debug!(LOG_DOMAIN, "Entered file {uri:#?}");
+                self.emit_by_name::<()>("select-uri", &[&uri]);
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -8,15 +8,19 @@
 
 use adw::prelude::*;
 use adw::subclass::prelude::*;
+use glib::clone;
 use glib::subclass::Signal;
 use glib::translate::*;
 use glib::Object;
 use glib_macros::Properties;
-use gtk::{gio, glib, CompositeTemplate};
+use gtk::{gdk, gio, glib, CompositeTemplate};
 use std::cell::RefCell;
 use std::sync::OnceLock;
 
-use crate::{config::LOG_DOMAIN, path_bar::PathBar, places_item::PlacesItem, util};
+use crate::{
+    bookmarks, bookmarks::Bookmark, config::LOG_DOMAIN, path_bar::PathBar, places_item::PlacesItem,
+    util,
+};
 
 mod imp {
     use super::*;
@@ -34,6 +38,15 @@ mod imp {
         // The folder to track
         #[property(get, set)]
         pub folder: RefCell<Option<gio::File>>,
+
+        pub(super) volume_monitor: RefCell<Option<gio::VolumeMonitor>>,
+        // Items for mounts and volumes, rebuilt on any change
+        pub(super) mount_items: RefCell<Vec<PlacesItem>>,
+
+        pub(super) bookmarks: RefCell<Vec<Bookmark>>,
+        pub(super) bookmarks_monitor: RefCell<Option<gio::FileMonitor>>,
+        pub(super) bookmark_items: RefCell<Vec<PlacesItem>>,
+        pub(super) bookmark_menu: RefCell<Option<gtk::PopoverMenu>>,
     }
 
     #[glib::object_subclass]
@@ -45,6 +58,24 @@ mod imp {
         fn class_init(klass: &mut Self::Class) {
             klass.bind_template();
             klass.bind_template_instance_callbacks();
+
+            klass.install_action(
+                "places.rename-bookmark",
+                Some(glib::VariantTy::STRING),
+                |this, _, param| {
+                    let uri: String = param.unwrap().get().unwrap();
+                    this.rename_bookmark(&uri);
+                },
+            );
+
+            klass.install_action(
+                "places.remove-bookmark",
+                Some(glib::VariantTy::STRING),
+                |this, _, param| {
+                    let uri: String = param.unwrap().get().unwrap();
+                    this.remove_bookmark(&uri);
+                },
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -83,9 +114,11 @@ mod imp {
                     continue;
                 }
 
-                let name = path.file_name().unwrap();
+                let Some(name) = path.file_name() else {
+                    continue;
+                };
                 let item = Object::builder::<PlacesItem>()
-                    .property("place", name.to_str())
+                    .property("place", glib::filename_display_name(name))
                     .property("icon-name", icon)
                     .property("uri", folder.uri())
                     .build();
@@ -99,15 +132,29 @@ mod imp {
                 .build();
             self.flow_box.append(&item);
 
-            // TODO: mounts, bookmarks, other locations
+            self.obj().setup_volume_monitor();
+            self.obj().setup_bookmarks();
+
+            // TODO: other locations
+        }
+
+        fn dispose(&self) {
+            if let Some(popover) = self.bookmark_menu.take() {
+                popover.unparent();
+            }
         }
 
         fn signals() -> &'static [Signal] {
             static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
             SIGNALS.get_or_init(|| {
-                vec![Signal::builder("new-uri")
-                    .param_types([String::static_type()])
-                    .build()]
+                vec![
+                    Signal::builder("new-uri")
+                        .param_types([String::static_type()])
+                        .build(),
+                    Signal::builder("select-uri")
+                        .param_types([String::static_type()])
+                        .build(),
+                ]
             })
         }
     }
@@ -134,19 +181,677 @@ impl PlacesBox {
         Self::default()
     }
 
+    fn setup_volume_monitor(&self) {
+        let monitor = gio::VolumeMonitor::get();
+
+        monitor.connect_volume_added(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+        monitor.connect_volume_removed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+        monitor.connect_volume_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+        monitor.connect_mount_added(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+        monitor.connect_mount_removed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+        monitor.connect_mount_changed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, _| this.update_mounts()
+        ));
+
+        *self.imp().volume_monitor.borrow_mut() = Some(monitor);
+        self.update_mounts();
+    }
+
+    fn update_mounts(&self) {
+        let imp = self.imp();
+        let Some(monitor) = imp.volume_monitor.borrow().clone() else {
+            return;
+        };
+
+        for item in imp.mount_items.take() {
+            imp.flow_box.remove(&item);
+        }
+
+        let mut items = Vec::new();
+        // Volumes, mounted or not
+        for volume in monitor.volumes() {
+            let mount = volume.get_mount();
+            let uri = mount
+                .as_ref()
+                .map(|mount| mount.root().uri().to_string())
+                .unwrap_or_default();
+            let can_eject =
+                volume.can_eject() || mount.as_ref().is_some_and(|mount| mount.can_eject());
+
+            let item = Object::builder::<PlacesItem>()
+                .property("place", volume.name())
+                .property("icon-name", icon_to_name(&volume.symbolic_icon()))
+                .property("uri", uri)
+                .property("volume", &volume)
+                .property("mount", mount)
+                .property("can-eject", can_eject)
+                .build();
+            items.push(item);
+        }
+
+        // Mounts without a volume like network shares
+        for mount in monitor.mounts() {
+            if mount.volume().is_some() || mount.is_shadowed() {
+                continue;
+            }
+
+            let item = Object::builder::<PlacesItem>()
+                .property("place", mount.name())
+                .property("icon-name", icon_to_name(&mount.symbolic_icon()))
+                .property("uri", mount.root().uri())
+                .property("mount", &mount)
+                .property("can-eject", mount.can_eject() || mount.can_unmount())
+                .build();
+            items.push(item);
+        }
+
+        for item in items.iter() {
+            item.connect_closure(
+                "eject",
+                false,
+                glib::closure_local!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |item: PlacesItem| {
+                        this.eject(&item);
+                    }
+                ),
+            );
+            imp.flow_box.append(item);
+        }
+
+        *imp.mount_items.borrow_mut() = items;
+    }
+
+    fn setup_bookmarks(&self) {
+        let file = bookmarks::bookmarks_file();
+
+        // Watch moves as the file gets replaced atomically when saved
+        match file.monitor_file(
+            gio::FileMonitorFlags::WATCH_MOVES,
+            None::<&gio::Cancellable>,
+        ) {
+            Ok(monitor) => {
+                monitor.connect_changed(clone!(
+                    #[weak(rename_to = this)]
+                    self,
+                    move |_, _, _, event| {
+                        match event {
+                            gio::FileMonitorEvent::ChangesDoneHint
+                            | gio::FileMonitorEvent::Created
+                            | gio::FileMonitorEvent::Deleted
+                            | gio::FileMonitorEvent::MovedIn
+                            | gio::FileMonitorEvent::Renamed => this.load_bookmarks(),
+                            _ => {}
+                        }
+                    }
+                ));
+                *self.imp().bookmarks_monitor.borrow_mut() = Some(monitor);
+            }
+            Err(err) => {
+                glib::g_warning!(LOG_DOMAIN, "Failed to monitor bookmarks: {err}");
+            }
+        }
+
+        self.load_bookmarks();
+    }
+
+    fn load_bookmarks(&self) {
+        let file = bookmarks::bookmarks_file();
+
+        file.load_contents_async(
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |result| {
+                    let bookmarks = match result {
+                        Ok((contents, _)) => bookmarks::parse(&String::from_utf8_lossy(&contents)),
+                        Err(err) => {
+                            if !err.matches(gio::IOErrorEnum::NotFound) {
+                                glib::g_warning!(LOG_DOMAIN, "Failed to load bookmarks: {err}");
+                            }
+                            Vec::new()
+                        }
+                    };
+
+                    *this.imp().bookmarks.borrow_mut() = bookmarks;
+                    this.update_bookmarks();
+                }
+            ),
+        );
+    }
+
+    fn save_bookmarks(&self) {
+        let file = bookmarks::bookmarks_file();
+        let contents = bookmarks::serialize(&self.imp().bookmarks.borrow());
+
+        if let Some(parent) = file.parent() {
+            if let Err(err) = parent.make_directory_with_parents(None::<&gio::Cancellable>) {
+                if !err.matches(gio::IOErrorEnum::Exists) {
+                    glib::g_warning!(LOG_DOMAIN, "Failed to create {:#?}: {err}", parent.uri());
+                }
+            }
+        }
+
+        file.replace_contents_async(
+            contents,
+            None,
+            false,
+            gio::FileCreateFlags::NONE,
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |result| {
+                    if let Err((_, err)) = result {
+                        this.show_error(&gettextrs::gettext("Failed to save bookmarks"), &err);
+                    }
+                }
+            ),
+        );
+
+        self.update_bookmarks();
+    }
+
+    fn update_bookmarks(&self) {
+        let imp = self.imp();
+
+        for item in imp.bookmark_items.take() {
+            imp.flow_box.remove(&item);
+        }
+
+        // Bookmarks go in front of mounts and volumes
+        let mut position = imp
+            .mount_items
+            .borrow()
+            .first()
+            .and_then(|item| item.parent())
+            .and_then(|child| child.downcast::<gtk::FlowBoxChild>().ok())
+            .map(|child| child.index())
+            .unwrap_or(-1);
+
+        let mut items = Vec::new();
+        for bookmark in imp.bookmarks.borrow().iter() {
+            let item = Object::builder::<PlacesItem>()
+                .property("place", bookmark.name())
+                .property("icon-name", bookmark.icon_name())
+                .property("uri", &bookmark.uri)
+                .build();
+
+            let click = gtk::GestureClick::builder()
+                .button(gdk::BUTTON_SECONDARY)
+                .build();
+            click.connect_pressed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                item,
+                move |gesture, _, _, _| {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    this.show_bookmark_menu(&item);
+                }
+            ));
+            item.add_controller(click);
+
+            let long_press = gtk::GestureLongPress::new();
+            long_press.connect_pressed(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                item,
+                move |gesture, _, _| {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    this.show_bookmark_menu(&item);
+                }
+            ));
+            item.add_controller(long_press);
+
+            imp.flow_box.insert(&item, position);
+            if position >= 0 {
+                position += 1;
+            }
+            items.push(item);
+        }
+
+        *imp.bookmark_items.borrow_mut() = items;
+    }
+
+    fn show_bookmark_menu(&self, item: &PlacesItem) {
+        let uri = item.uri().to_variant();
+        let menu = gio::Menu::new();
+        menu.append(
+            Some(&gettextrs::gettext("_Rename…")),
+            Some(&gio::Action::print_detailed_name(
+                "places.rename-bookmark",
+                Some(&uri),
+            )),
+        );
+        menu.append(
+            Some(&gettextrs::gettext("Re_move")),
+            Some(&gio::Action::print_detailed_name(
+                "places.remove-bookmark",
+                Some(&uri),
+            )),
+        );
+
+        let imp = self.imp();
+        let popover = imp
+            .bookmark_menu
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                let popover = gtk::PopoverMenu::from_model(None::<&gio::MenuModel>);
+                popover.set_parent(self);
+                popover
+            })
+            .clone();
+
+        popover.set_menu_model(Some(&menu));
+        if let Some(bounds) = item.compute_bounds(self) {
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(
+                bounds.x() as i32,
+                bounds.y() as i32,
+                bounds.width() as i32,
+                bounds.height() as i32,
+            )));
+        }
+        popover.popup();
+    }
+
+    pub(crate) fn add_bookmark(&self, folder: &gio::File) {
+        let uri = folder.uri();
+
+        {
+            let mut bookmarks = self.imp().bookmarks.borrow_mut();
+            if bookmarks.iter().any(|bookmark| bookmark.uri == uri) {
+                return;
+            }
+            bookmarks.push(Bookmark::new(&uri));
+        }
+
+        glib::g_debug!(LOG_DOMAIN, "Bookmarking {uri:#?}");
+        self.save_bookmarks();
+    }
+
+    fn remove_bookmark(&self, uri: &str) {
+        self.imp()
+            .bookmarks
+            .borrow_mut()
+            .retain(|bookmark| bookmark.uri != uri);
+
+        glib::g_debug!(LOG_DOMAIN, "Removed bookmark {uri:#?}");
+        self.save_bookmarks();
+    }
+
+    fn rename_bookmark(&self, uri: &str) {
+        let Some(bookmark) = self
+            .imp()
+            .bookmarks
+            .borrow()
+            .iter()
+            .find(|bookmark| bookmark.uri == uri)
+            .cloned()
+        else {
+            return;
+        };
+
+        let entry = gtk::Entry::builder()
+            .text(bookmark.name())
+            .activates_default(true)
+            .build();
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettextrs::gettext("Rename Bookmark"))
+            .extra_child(&entry)
+            .close_response("cancel")
+            .default_response("rename")
+            .build();
+
+        dialog.add_response("cancel", &gettextrs::gettext("Cancel"));
+        dialog.add_response("rename", &gettextrs::gettext("_Rename"));
+        dialog.set_response_appearance("rename", adw::ResponseAppearance::Suggested);
+
+        dialog.choose(
+            self,
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |response| {
+                    if response != "rename" {
+                        return;
+                    }
+
+                    // An empty label reverts to the name derived from the URI
+                    let label = entry.text().trim().to_string();
+                    let label = (!label.is_empty()).then_some(label);
+
+                    for existing in this.imp().bookmarks.borrow_mut().iter_mut() {
+                        if existing.uri == bookmark.uri {
+                            existing.label = label.clone();
+                        }
+                    }
+                    this.save_bookmarks();
+                }
+            ),
+        );
+    }
+
+    fn mount_operation(&self) -> gio::MountOperation {
+        let operation = gio::MountOperation::new();
+
+        operation.connect_ask_password(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |operation, message, default_user, default_domain, flags| {
+                this.ask_password(operation, message, default_user, default_domain, flags);
+            }
+        ));
+
+        operation
+    }
+
+    fn ask_password(
+        &self,
+        operation: &gio::MountOperation,
+        message: &str,
+        default_user: &str,
+        default_domain: &str,
+        flags: gio::AskPasswordFlags,
+    ) {
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+
+        let anonymous = adw::SwitchRow::builder()
+            .title(gettextrs::gettext("Connect _Anonymously"))
+            .use_underline(true)
+            .visible(flags.contains(gio::AskPasswordFlags::ANONYMOUS_SUPPORTED))
+            .build();
+        list.append(&anonymous);
+
+        let username = adw::EntryRow::builder()
+            .title(gettextrs::gettext("_Username"))
+            .use_underline(true)
+            .text(default_user)
+            .visible(flags.contains(gio::AskPasswordFlags::NEED_USERNAME))
+            .build();
+        list.append(&username);
+
+        let domain = adw::EntryRow::builder()
+            .title(gettextrs::gettext("_Domain"))
+            .use_underline(true)
+            .text(default_domain)
+            .visible(flags.contains(gio::AskPasswordFlags::NEED_DOMAIN))
+            .build();
+        list.append(&domain);
+
+        let password = adw::PasswordEntryRow::builder()
+            .title(gettextrs::gettext("_Password"))
+            .use_underline(true)
+            .activates_default(true)
+            .visible(flags.contains(gio::AskPasswordFlags::NEED_PASSWORD))
+            .build();
+        list.append(&password);
+
+        let remember = adw::SwitchRow::builder()
+            .title(gettextrs::gettext("_Remember Password"))
+            .use_underline(true)
+            .visible(flags.contains(gio::AskPasswordFlags::SAVING_SUPPORTED))
+            .build();
+        list.append(&remember);
+
+        for row in [
+            username.upcast_ref::<gtk::Widget>(),
+            domain.upcast_ref(),
+            password.upcast_ref(),
+            remember.upcast_ref(),
+        ] {
+            anonymous
+                .bind_property("active", row, "sensitive")
+                .invert_boolean()
+                .sync_create()
+                .build();
+        }
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettextrs::gettext("Authentication Required"))
+            .body(message)
+            .extra_child(&list)
+            .close_response("cancel")
+            .default_response("connect")
+            .build();
+
+        dialog.add_response("cancel", &gettextrs::gettext("Cancel"));
+        dialog.add_response("connect", &gettextrs::gettext("C_onnect"));
+        dialog.set_response_appearance("connect", adw::ResponseAppearance::Suggested);
+
+        let operation = operation.clone();
+        dialog.choose(self, None::<&gio::Cancellable>, move |response| {
+            if response != "connect" {
+                operation.reply(gio::MountOperationResult::Aborted);
+                return;
+            }
+
+            if anonymous.is_active() {
+                operation.set_anonymous(true);
+            } else {
+                operation.set_username(Some(&username.text()));
+                operation.set_domain(Some(&domain.text()));
+                operation.set_password(Some(&password.text()));
+                if remember.is_active() {
+                    operation.set_password_save(gio::PasswordSave::Permanently);
+                }
+            }
+            operation.reply(gio::MountOperationResult::Handled);
+        });
+    }
+
+    fn mount_volume(&self, volume: &gio::Volume) {
+        glib::g_debug!(LOG_DOMAIN, "Mounting {:#?}", volume.name());
+
+        volume.mount(
+            gio::MountMountFlags::NONE,
+            Some(&self.mount_operation()),
+            None::<&gio::Cancellable>,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[strong]
+                volume,
+                move |result| {
+                    if let Err(err) = result {
+                        this.show_error(&gettextrs::gettext("Failed to mount volume"), &err);
+                        return;
+                    }
+
+                    let Some(mount) = volume.get_mount() else {
+                        return;
+                    };
+                    let uri = mount.root().uri();
+                    this.emit_by_name::<()>("new-uri", &[&uri]);
+                }
+            ),
+        );
+    }
+
+    fn eject(&self, item: &PlacesItem) {
+        let callback = clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            item,
+            move |result: Result<(), glib::Error>| {
+                if let Err(err) = result {
+                    this.show_error(&gettextrs::gettext("Failed to eject"), &err);
+                    return;
+                }
+
+                glib::g_debug!(LOG_DOMAIN, "Ejected {:#?}", item.place());
+            }
+        );
+
+        // Don't keep the user on a folder that is about to go away
+        if let Some(mount) = item.mount() {
+            let root = mount.root();
+            let on_mount = self
+                .folder()
+                .is_some_and(|folder| folder.equal(&root) || folder.has_prefix(&root));
+            if on_mount {
+                let home = gio::File::for_path(glib::home_dir());
+                self.emit_by_name::<()>("new-uri", &[&home.uri()]);
+            }
+        }
+
+        let operation = self.mount_operation();
+        let flags = gio::MountUnmountFlags::NONE;
+        match (item.volume(), item.mount()) {
+            (_, Some(mount)) if mount.can_eject() => mount.eject_with_operation(
+                flags,
+                Some(&operation),
+                None::<&gio::Cancellable>,
+                callback,
+            ),
+            (Some(volume), _) if volume.can_eject() => volume.eject_with_operation(
+                flags,
+                Some(&operation),
+                None::<&gio::Cancellable>,
+                callback,
+            ),
+            (_, Some(mount)) => mount.unmount_with_operation(
+                flags,
+                Some(&operation),
+                None::<&gio::Cancellable>,
+                callback,
+            ),
+            _ => {}
+        }
+    }
+
+    fn show_error(&self, heading: &str, err: &glib::Error) {
+        // The mount operation already informed the user
+        if err.matches(gio::IOErrorEnum::FailedHandled) {
+            return;
+        }
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(heading)
+            .body(err.message())
+            .close_response("close")
+            .default_response("close")
+            .build();
+
+        dialog.add_response("close", &gettextrs::gettext("_Close"));
+        dialog.present(Some(self));
+    }
+
     #[template_callback]
     fn on_item_activated(&self, flowboxchild: gtk::FlowBoxChild) {
         let object = flowboxchild.child().unwrap();
         let item = object.downcast_ref::<PlacesItem>().unwrap();
 
+        // Unmounted volume, mount it first
+        if item.mount().is_none() {
+            if let Some(volume) = item.volume() {
+                self.mount_volume(&volume);
+                return;
+            }
+        }
+
         let uri: String = item.uri();
+        // Remote bookmarks might need mounting
+        let file = gio::File::for_uri(&uri);
+        if !file.is_native() && self.imp().bookmark_items.borrow().contains(item) {
+            self.open_remote(file);
+            return;
+        }
+
         glib::g_debug!(LOG_DOMAIN, "Should open {uri:#?}");
         self.imp().obj().emit_by_name::<()>("new-uri", &[&uri]);
     }
 
+    // Open a remote location mounting it first if needed
+    fn open_remote(&self, file: gio::File) {
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to = this)]
+            self,
+            async move {
+                let result = file
+                    .query_info_future(
+                        "standard::type",
+                        gio::FileQueryInfoFlags::NONE,
+                        glib::Priority::DEFAULT,
+                    )
+                    .await;
+
+                if let Err(err) = result {
+                    if !err.matches(gio::IOErrorEnum::NotMounted) {
+                        this.show_error(&gettextrs::gettext("Failed to open location"), &err);
+                        return;
+                    }
+
+                    glib::g_debug!(LOG_DOMAIN, "Mounting {:#?}", file.uri());
+                    if let Err(err) = file
+                        .mount_enclosing_volume_future(
+                            gio::MountMountFlags::NONE,
+                            Some(&this.mount_operation()),
+                        )
+                        .await
+                    {
+                        this.show_error(&gettextrs::gettext("Failed to mount location"), &err);
+                        return;
+                    }
+                }
+
+                let uri = file.uri();
+                glib::g_debug!(LOG_DOMAIN, "Should open {uri:#?}");
+                this.emit_by_name::<()>("new-uri", &[&uri]);
+            }
+        ));
+    }
+
     #[template_callback]
     fn on_new_uri(&self, uri: String) {
         // Pass on new uri from path bar
         self.imp().obj().emit_by_name::<()>("new-uri", &[&uri]);
     }
+
+    #[template_callback]
+    fn on_select_uri(&self, uri: String) {
+        // Pass on file entered in the path bar
+        self.imp().obj().emit_by_name::<()>("select-uri", &[&uri]);
+    }
+
+    pub(crate) fn edit_location(&self, text: Option<&str>) {
+        self.imp().path_bar.edit_location(text);
+    }
+}
+
+fn icon_to_name(icon: &gio::Icon) -> String {
+    icon.downcast_ref::<gio::ThemedIcon>()
+        .and_then(|icon| icon.names().first().map(|name| name.to_string()))
+        .unwrap_or_else(|| "drive-harddisk-symbolic".to_string())
 }
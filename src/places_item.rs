@@ -8,9 +8,11 @@
 
 use adw::prelude::*;
 use adw::subclass::prelude::*;
+use glib::subclass::Signal;
 use glib_macros::Properties;
-use gtk::{glib, CompositeTemplate};
-use std::cell::RefCell;
+use gtk::{gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
+use std::sync::OnceLock;
 
 mod imp {
     use super::*;
@@ -33,6 +35,17 @@ mod imp {
 
         #[property(get, set)]
         uri: RefCell<String>,
+
+        // The volume backing this place (if any)
+        #[property(get, set, nullable)]
+        volume: RefCell<Option<gio::Volume>>,
+
+        // The mount backing this place (if any)
+        #[property(get, set, nullable)]
+        mount: RefCell<Option<gio::Mount>>,
+
+        #[property(get, set)]
+        can_eject: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -58,6 +71,11 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
         }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| vec![Signal::builder("eject").build()])
+        }
     }
 
     impl WidgetImpl for PlacesItem {}
@@ -80,4 +98,9 @@ impl PlacesItem {
     pub fn new() -> Self {
         Self::default()
     }
+
+    #[template_callback]
+    fn on_eject_clicked(&self) {
+        self.emit_by_name::<()>("eject", &[]);
+    }
 }
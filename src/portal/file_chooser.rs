@@ -0,0 +1,301 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use adw::prelude::*;
+use gtk::{gio, glib};
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use pfs::file_selector::{FileSelector, FileSelectorMode};
+
+use crate::config::LOG_DOMAIN;
+use crate::request;
+
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const INTERFACE_NAME: &str = "org.freedesktop.impl.portal.FileChooser";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.freedesktop.impl.portal.FileChooser">
+    <method name="OpenFile">
+      <arg type="o" name="handle" direction="in"/>
+      <arg type="s" name="app_id" direction="in"/>
+      <arg type="s" name="parent_window" direction="in"/>
+      <arg type="s" name="title" direction="in"/>
+      <arg type="a{sv}" name="options" direction="in"/>
+      <arg type="u" name="response" direction="out"/>
+      <arg type="a{sv}" name="results" direction="out"/>
+    </method>
+    <method name="SaveFile">
+      <arg type="o" name="handle" direction="in"/>
+      <arg type="s" name="app_id" direction="in"/>
+      <arg type="s" name="parent_window" direction="in"/>
+      <arg type="s" name="title" direction="in"/>
+      <arg type="a{sv}" name="options" direction="in"/>
+      <arg type="u" name="response" direction="out"/>
+      <arg type="a{sv}" name="results" direction="out"/>
+    </method>
+    <method name="SaveFiles">
+      <arg type="o" name="handle" direction="in"/>
+      <arg type="s" name="app_id" direction="in"/>
+      <arg type="s" name="parent_window" direction="in"/>
+      <arg type="s" name="title" direction="in"/>
+      <arg type="a{sv}" name="options" direction="in"/>
+      <arg type="u" name="response" direction="out"/>
+      <arg type="a{sv}" name="results" direction="out"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+// Response codes as defined by the portal spec
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Response {
+    Success = 0,
+    Cancelled = 1,
+}
+
+type RequestArgs = (
+    glib::variant::ObjectPath,
+    String,
+    String,
+    String,
+    glib::VariantDict,
+);
+
+pub fn register(connection: &gio::DBusConnection) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info.lookup_interface(INTERFACE_NAME).unwrap();
+
+    connection
+        .register_object(OBJECT_PATH, &interface_info)
+        .method_call(
+            move |_, sender, _, _, method_name, parameters, invocation| {
+                glib::g_debug!(LOG_DOMAIN, "{method_name} from {sender:#?}");
+
+                let mode = match method_name {
+                    "OpenFile" => FileSelectorMode::OpenFile,
+                    "SaveFile" => FileSelectorMode::SaveFile,
+                    "SaveFiles" => FileSelectorMode::SaveFiles,
+                    _ => {
+                        invocation.return_dbus_error(
+                            "org.freedesktop.DBus.Error.UnknownMethod",
+                            &format!("Unknown method {method_name}"),
+                        );
+                        return;
+                    }
+                };
+
+                let Some(args) = parameters.get::<RequestArgs>() else {
+                    invocation.return_dbus_error(
+                        "org.freedesktop.DBus.Error.InvalidArgs",
+                        &format!("Invalid arguments for {method_name}"),
+                    );
+                    return;
+                };
+
+                handle_request(mode, args, invocation);
+            },
+        )
+        .build()
+}
+
+// Turn a NUL terminated byte string into a path keeping non UTF-8 names intact
+fn bytestring_to_path(variant: &glib::Variant) -> Option<PathBuf> {
+    let bytes = variant.fixed_array::<u8>().ok()?;
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+
+    if bytes.is_empty() {
+        return None;
+    }
+
+    Some(PathBuf::from(OsStr::from_bytes(bytes)))
+}
+
+fn lookup_path(options: &glib::VariantDict, key: &str) -> Option<PathBuf> {
+    let value = options.lookup_value(key, Some(glib::VariantTy::BYTE_STRING))?;
+    bytestring_to_path(&value)
+}
+
+fn handle_request(
+    mode: FileSelectorMode,
+    args: RequestArgs,
+    invocation: gio::DBusMethodInvocation,
+) {
+    let (handle, app_id, parent_window, title, options) = args;
+
+    glib::g_debug!(
+        LOG_DOMAIN,
+        "Request {:#?} for {app_id:#?}, parent {parent_window:#?}",
+        handle.as_str()
+    );
+
+    let default_label = match mode {
+        FileSelectorMode::OpenFile => gettextrs::gettext("_Open"),
+        FileSelectorMode::SaveFile => gettextrs::gettext("_Save"),
+        FileSelectorMode::SaveFiles => gettextrs::gettext("_Save"),
+    };
+    let accept_label = options
+        .lookup::<String>("accept_label")
+        .ok()
+        .flatten()
+        .unwrap_or(default_label);
+    let modal = options
+        .lookup::<bool>("modal")
+        .ok()
+        .flatten()
+        .unwrap_or(true);
+    let multiple = options
+        .lookup::<bool>("multiple")
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let directory = options
+        .lookup::<bool>("directory")
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let current_name = options.lookup::<String>("current_name").ok().flatten();
+    let choices = options.lookup_value(
+        "choices",
+        Some(glib::VariantTy::new("a(ssa(ss)s)").unwrap()),
+    );
+
+    let mut current_folder = lookup_path(&options, "current_folder");
+    let mut filename = current_name;
+    // Saving to an existing file trumps folder and name
+    let current_file = lookup_path(&options, "current_file");
+    if let Some(current_file) = current_file.as_ref() {
+        current_folder = current_file.parent().map(|parent| parent.to_path_buf());
+        filename = current_file
+            .file_name()
+            .map(|name| glib::filename_display_name(name).to_string());
+    }
+
+    let filters = options.lookup_value("filters", None);
+    let current_filter = options.lookup_value("current_filter", None);
+
+    // `parent_window` isn't honoured: making the selector transient for a
+    // foreign `x11:` or `wayland:` window needs the backend specific gdk
+    // crates and the xdg-foreign protocol. Log it so it's obvious why the
+    // selector isn't stacked on top of the app's window.
+    if !parent_window.is_empty() {
+        glib::g_debug!(LOG_DOMAIN, "Ignoring parent window {parent_window:#?}");
+    }
+
+    let file_selector = glib::Object::builder::<FileSelector>()
+        .property("title", &title)
+        .property("accept-label", &accept_label)
+        .property("modal", modal)
+        .property("app-id", &app_id)
+        .build();
+    // Without a folder the selector picks the one last used by the app
+    if let Some(current_folder) = current_folder {
+        file_selector.set_current_folder(gio::File::for_path(current_folder));
+    }
+    file_selector.set_mode(mode);
+
+    if filters.is_some() || current_filter.is_some() {
+        file_selector.set_portal_filters(filters.as_ref(), current_filter.as_ref());
+    }
+
+    if mode == FileSelectorMode::OpenFile {
+        file_selector.set_directory(directory);
+        file_selector.set_multiple(multiple);
+    }
+
+    if let Some(choices) = choices {
+        file_selector.set_choices(&choices);
+    }
+
+    if mode == FileSelectorMode::SaveFiles {
+        if let Some(files) = options.lookup_value("files", Some(glib::VariantTy::BYTE_STRING_ARRAY))
+        {
+            let names = files
+                .iter()
+                .filter_map(|file| bytestring_to_path(&file))
+                .filter_map(|path| path.file_name().map(PathBuf::from))
+                .collect::<Vec<PathBuf>>();
+            file_selector.set_files_from_paths(&names);
+        }
+    }
+
+    if let Some(filename) = filename {
+        if mode == FileSelectorMode::SaveFile {
+            file_selector.set_filename(filename);
+        }
+    }
+
+    // Selecting the existing file keeps its name's raw bytes
+    if let Some(current_file) = current_file {
+        if mode == FileSelectorMode::SaveFile {
+            file_selector.select_file(&gio::File::for_path(current_file));
+        }
+    }
+
+    let connection = invocation.connection();
+    let registration = match request::export(&connection, handle.as_str(), &file_selector) {
+        Ok(id) => Some(id),
+        Err(err) => {
+            glib::g_warning!(LOG_DOMAIN, "Failed to export request: {err}");
+            None
+        }
+    };
+    let registration = RefCell::new(registration);
+
+    // The invocation can only be answered once
+    let invocation = RefCell::new(Some(invocation));
+    file_selector.connect_closure(
+        "done",
+        false,
+        glib::closure_local!(move |selector: FileSelector, success: bool| {
+            if let Some(id) = registration.take() {
+                let _ = connection.unregister_object(id);
+            }
+
+            let Some(invocation) = invocation.take() else {
+                return;
+            };
+
+            // Skipping all files leaves nothing to save
+            let success = success && selector.selected().is_some();
+            let (response, results) = if success {
+                (Response::Success, build_results(&selector))
+            } else {
+                (Response::Cancelled, glib::VariantDict::new(None).end())
+            };
+
+            glib::g_debug!(LOG_DOMAIN, "Responding with {response:#?}");
+            invocation.return_value(Some(&glib::Variant::tuple_from_iter([
+                (response as u32).to_variant(),
+                results,
+            ])));
+        }),
+    );
+
+    file_selector.present();
+}
+
+fn build_results(file_selector: &FileSelector) -> glib::Variant {
+    let results = glib::VariantDict::new(None);
+
+    let uris = file_selector.selected().unwrap_or_default();
+    results.insert_value("uris", &uris.to_variant());
+
+    if let Some(choices) = file_selector.selected_choices() {
+        results.insert_value("choices", &choices);
+    }
+
+    if let Some(current_filter) = file_selector.portal_current_filter() {
+        results.insert_value("current_filter", &current_filter);
+    }
+
+    results.end()
+}
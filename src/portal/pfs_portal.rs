@@ -0,0 +1,32 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+mod config;
+mod file_chooser;
+mod pfs_portal_application;
+mod request;
+
+use self::pfs_portal_application::PfsPortalApplication;
+
+use config::{GETTEXT_PACKAGE, LOCALEDIR};
+use gettextrs::{bind_textdomain_codeset, bindtextdomain, textdomain};
+use gtk::prelude::*;
+use gtk::{gio, glib};
+
+fn main() -> glib::ExitCode {
+    bindtextdomain(GETTEXT_PACKAGE, LOCALEDIR).expect("Unable to bind the text domain");
+    bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8")
+        .expect("Unable to set the text domain encoding");
+    textdomain(GETTEXT_PACKAGE).expect("Unable to switch to the text domain");
+
+    pfs::init::init();
+
+    let app = PfsPortalApplication::new(&gio::ApplicationFlags::NON_UNIQUE);
+    glib::set_prgname(Some("pfs-portal"));
+    app.run()
+}
@@ -0,0 +1,127 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib_macros::clone;
+use gtk::{gio, glib};
+use std::cell::{Cell, RefCell};
+
+use crate::config::LOG_DOMAIN;
+use crate::file_chooser;
+
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.pfs";
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct PfsPortalApplication {
+        pub hold_guard: RefCell<Option<gio::ApplicationHoldGuard>>,
+        pub owner_id: RefCell<Option<gio::OwnerId>>,
+        pub replace: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PfsPortalApplication {
+        const NAME: &'static str = "PfsPortalApplication";
+        type Type = super::PfsPortalApplication;
+        type ParentType = adw::Application;
+    }
+
+    impl ObjectImpl for PfsPortalApplication {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.obj().add_main_option(
+                "replace",
+                glib::Char::from(b'r'),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                "Replace a running instance",
+                None,
+            );
+        }
+    }
+
+    impl ApplicationImpl for PfsPortalApplication {
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            self.replace.replace(options.contains("replace"));
+            self.parent_handle_local_options(options)
+        }
+
+        fn startup(&self) {
+            self.parent_startup();
+
+            let application = self.obj();
+            // We're a service, stay around until we lose the bus name
+            *self.hold_guard.borrow_mut() = Some(application.hold());
+            application.own_name();
+        }
+
+        fn activate(&self) {}
+
+        fn shutdown(&self) {
+            if let Some(owner_id) = self.owner_id.take() {
+                gio::bus_unown_name(owner_id);
+            }
+            self.parent_shutdown();
+        }
+    }
+
+    impl GtkApplicationImpl for PfsPortalApplication {}
+    impl AdwApplicationImpl for PfsPortalApplication {}
+}
+
+glib::wrapper! {
+    pub struct PfsPortalApplication(ObjectSubclass<imp::PfsPortalApplication>)
+        @extends gio::Application, gtk::Application, adw::Application,
+        @implements gio::ActionGroup, gio::ActionMap;
+}
+
+impl PfsPortalApplication {
+    pub fn new(flags: &gio::ApplicationFlags) -> Self {
+        glib::Object::builder().property("flags", flags).build()
+    }
+
+    fn own_name(&self) {
+        let mut flags = gio::BusNameOwnerFlags::ALLOW_REPLACEMENT;
+        if self.imp().replace.get() {
+            flags |= gio::BusNameOwnerFlags::REPLACE;
+        }
+
+        let owner_id = gio::bus_own_name(
+            gio::BusType::Session,
+            BUS_NAME,
+            flags,
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |connection, _| {
+                    if let Err(err) = file_chooser::register(&connection) {
+                        glib::g_critical!(LOG_DOMAIN, "Failed to export file chooser: {err}");
+                        this.quit();
+                    }
+                }
+            ),
+            move |_, name| {
+                glib::g_debug!(LOG_DOMAIN, "Acquired {name:#?}");
+            },
+            clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_, name| {
+                    glib::g_warning!(LOG_DOMAIN, "Lost {name:#?}, exiting");
+                    this.imp().hold_guard.replace(None);
+                    this.quit();
+                }
+            ),
+        );
+        *self.imp().owner_id.borrow_mut() = Some(owner_id);
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use adw::prelude::*;
+use glib_macros::clone;
+use gtk::{gio, glib};
+
+use pfs::file_selector::FileSelector;
+
+use crate::config::LOG_DOMAIN;
+
+const INTERFACE_NAME: &str = "org.freedesktop.impl.portal.Request";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.freedesktop.impl.portal.Request">
+    <method name="Close"/>
+  </interface>
+</node>
+"#;
+
+// Export a request object at `handle` so the frontend can dismiss the file selector
+pub fn export(
+    connection: &gio::DBusConnection,
+    handle: &str,
+    file_selector: &FileSelector,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info.lookup_interface(INTERFACE_NAME).unwrap();
+
+    connection
+        .register_object(handle, &interface_info)
+        .method_call(clone!(
+            #[weak]
+            file_selector,
+            move |_, _, object_path, _, method_name, _, invocation| {
+                if method_name != "Close" {
+                    invocation.return_dbus_error(
+                        "org.freedesktop.DBus.Error.UnknownMethod",
+                        &format!("Unknown method {method_name}"),
+                    );
+                    return;
+                }
+
+                glib::g_debug!(LOG_DOMAIN, "Closing request {object_path:#?}");
+                file_selector.cancel();
+                invocation.return_value(None);
+            }
+        ))
+        .build()
+}
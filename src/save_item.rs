@@ -0,0 +1,279 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::translate::*;
+use glib_macros::{clone, Properties};
+use gtk::{gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+
+use crate::{config::LOG_DOMAIN, util};
+
+// What to do with a file that already exists in the target folder
+#[derive(Debug, Copy, Clone, Default, PartialEq, gio::glib::Enum)]
+#[enum_type(name = "PfsSaveItemAction")]
+pub enum SaveAction {
+    #[default]
+    Rename = 0,
+    Overwrite = 1,
+    Skip = 2,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate, Properties)]
+    #[template(resource = "/mobi/phosh/FileSelector/save-item.ui")]
+    #[properties(wrapper_type = super::SaveItem)]
+    pub struct SaveItem {
+        #[template_child]
+        pub action_dropdown: TemplateChild<gtk::DropDown>,
+
+        // The name the application asked for
+        #[property(get, set = Self::set_original_name)]
+        original_name: RefCell<String>,
+
+        // The folder the file will be saved to
+        #[property(get, set = Self::set_folder, explicit_notify, nullable)]
+        folder: RefCell<Option<gio::File>>,
+
+        // `true` if a file with the current name exists in `folder`
+        #[property(get, explicit_notify)]
+        exists: Cell<bool>,
+
+        // How to handle an existing file
+        #[property(get, set = Self::set_action, explicit_notify, builder(SaveAction::default()))]
+        action: Cell<SaveAction>,
+
+        // `true` if another item in the list saves to the same file
+        #[property(get, set = Self::set_duplicate, explicit_notify)]
+        duplicate: Cell<bool>,
+
+        // `true` if the file can be saved (or is skipped)
+        #[property(get, explicit_notify)]
+        resolved: Cell<bool>,
+
+        // The original name as is, in case it isn't valid UTF-8
+        pub(super) raw_name: RefCell<Option<PathBuf>>,
+
+        // Cancels the pending check whether the file exists
+        exists_cancellable: RefCell<Option<gio::Cancellable>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SaveItem {
+        const NAME: &'static str = "PfsSaveItem";
+        type Type = super::SaveItem;
+        type ParentType = adw::EntryRow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl SaveItem {
+        fn set_original_name(&self, name: String) {
+            let obj = self.obj();
+
+            obj.set_text(&name);
+            *self.original_name.borrow_mut() = name;
+        }
+
+        fn set_folder(&self, folder: Option<gio::File>) {
+            if *self.folder.borrow() == folder {
+                return;
+            }
+
+            *self.folder.borrow_mut() = folder;
+            self.obj().notify_folder();
+            self.update();
+        }
+
+        fn set_action(&self, action: SaveAction) {
+            if self.action.get() == action {
+                return;
+            }
+
+            self.action.replace(action);
+            self.obj().notify_action();
+            self.update();
+        }
+
+        fn set_duplicate(&self, duplicate: bool) {
+            if self.duplicate.get() == duplicate {
+                return;
+            }
+
+            self.duplicate.replace(duplicate);
+            self.obj().notify_duplicate();
+            self.update_state();
+        }
+
+        fn set_exists(&self, exists: bool) {
+            if self.exists.get() != exists {
+                self.exists.replace(exists);
+                self.obj().notify_exists();
+            }
+            self.update_state();
+        }
+
+        // Check whether the file exists without blocking on slow folders
+        pub(super) fn update(&self) {
+            let obj = self.obj();
+            let name = obj.text();
+
+            if let Some(cancellable) = self.exists_cancellable.take() {
+                cancellable.cancel();
+            }
+
+            let file = match (name.is_empty(), self.folder.borrow().as_ref()) {
+                (false, Some(folder)) => obj.file(folder),
+                _ => {
+                    self.set_exists(false);
+                    return;
+                }
+            };
+
+            // Not resolved until we know
+            let cancellable = gio::Cancellable::new();
+            self.exists_cancellable.replace(Some(cancellable.clone()));
+            self.update_state();
+
+            glib::spawn_future_local(clone!(
+                #[weak(rename_to = this)]
+                self,
+                async move {
+                    let info = file.query_info_future(
+                        "standard::type",
+                        gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+                        glib::Priority::DEFAULT,
+                    );
+                    let Ok(result) = gio::CancellableFuture::new(info, cancellable).await else {
+                        return;
+                    };
+
+                    this.exists_cancellable.replace(None);
+                    this.set_exists(result.is_ok());
+                }
+            ));
+        }
+
+        fn update_state(&self) {
+            let obj = self.obj();
+            let name = obj.text();
+            let exists = self.exists.get();
+            let checking = self.exists_cancellable.borrow().is_some();
+
+            let valid = util::validate_filename(&name).is_ok();
+            if valid && !(self.duplicate.get() && !obj.skipped()) {
+                obj.remove_css_class("error");
+            } else {
+                obj.add_css_class("error");
+            }
+
+            // Existing files need an explicit decision, renaming resolves by changing
+            // the name. Two items can't be saved to the same file.
+            let resolved = match self.action.get() {
+                _ if checking => false,
+                SaveAction::Skip if exists => true,
+                _ if self.duplicate.get() => false,
+                SaveAction::Overwrite if exists => valid,
+                _ => valid && !exists,
+            };
+
+            if self.resolved.get() != resolved {
+                glib::g_debug!(LOG_DOMAIN, "{name:#?} resolved: {resolved:#?}");
+                self.resolved.replace(resolved);
+                obj.notify_resolved();
+            }
+
+            if obj.skipped() {
+                obj.add_css_class("dim-label");
+            } else {
+                obj.remove_css_class("dim-label");
+            }
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for SaveItem {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            obj.bind_property("action", &self.action_dropdown.get(), "selected")
+                .sync_create()
+                .bidirectional()
+                .transform_to(|_, action: SaveAction| Some(action.into_glib() as u32))
+                .transform_from(|_, selected: u32| {
+                    Some(unsafe { SaveAction::from_glib(selected as i32) })
+                })
+                .build();
+
+            self.update();
+        }
+    }
+
+    impl WidgetImpl for SaveItem {}
+    impl ListBoxRowImpl for SaveItem {}
+    impl PreferencesRowImpl for SaveItem {}
+    impl EntryRowImpl for SaveItem {}
+}
+
+glib::wrapper! {
+    pub struct SaveItem(ObjectSubclass<imp::SaveItem>)
+        @extends adw::EntryRow, adw::PreferencesRow, gtk::ListBoxRow, gtk::Widget,
+        @implements gtk::Editable;
+}
+
+impl Default for SaveItem {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl SaveItem {
+    pub fn new(name: &str) -> Self {
+        glib::Object::builder()
+            .property("original-name", name)
+            .build()
+    }
+
+    pub fn set_raw_name(&self, name: &Path) {
+        *self.imp().raw_name.borrow_mut() = Some(name.to_path_buf());
+        self.imp().update();
+    }
+
+    // The file to save to in `folder`, unchanged names keep their raw bytes
+    pub fn file(&self, folder: &gio::File) -> gio::File {
+        let raw_name = self.imp().raw_name.borrow().clone();
+
+        match raw_name {
+            Some(raw_name) if self.text() == self.original_name() => folder.child(raw_name),
+            _ => folder.child(self.text().as_str()),
+        }
+    }
+
+    // Whether the user chose to not save this file
+    pub fn skipped(&self) -> bool {
+        self.exists() && self.action() == SaveAction::Skip
+    }
+
+    #[template_callback]
+    fn on_changed(&self) {
+        self.imp().update();
+    }
+}
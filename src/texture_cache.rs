@@ -0,0 +1,91 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+// Loads images off the main thread and keeps the recently used ones
+// around so scrolling back and forth doesn't hit the disk again.
+
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// How many textures to keep around
+const MAX_ENTRIES: usize = 256;
+
+// A file's path and its modification time so changed files get reloaded
+type Key = (PathBuf, u64);
+
+#[derive(Debug, Default)]
+pub struct TextureCache {
+    // Least recently used entries first
+    entries: RefCell<VecDeque<(Key, gdk::Texture)>>,
+}
+
+thread_local! {
+    static TEXTURE_CACHE: Rc<TextureCache> = Rc::new(TextureCache::default());
+}
+
+impl TextureCache {
+    pub fn get() -> Rc<TextureCache> {
+        TEXTURE_CACHE.with(Rc::clone)
+    }
+
+    pub fn lookup(&self, path: &Path, mtime: u64) -> Option<gdk::Texture> {
+        let mut entries = self.entries.borrow_mut();
+        let pos = entries
+            .iter()
+            .position(|((p, m), _)| p == path && *m == mtime)?;
+
+        // Mark as most recently used
+        let entry = entries.remove(pos)?;
+        let texture = entry.1.clone();
+        entries.push_back(entry);
+        Some(texture)
+    }
+
+    fn insert(&self, path: &Path, mtime: u64, texture: &gdk::Texture) {
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|((p, _), _)| p != path);
+        entries.push_back(((path.to_path_buf(), mtime), texture.clone()));
+
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+    }
+
+    // Load the image at `path` in a worker thread unless it's cached already.
+    // `mtime` is the modification time of the file the image was made for.
+    pub async fn load(
+        &self,
+        path: &Path,
+        mtime: u64,
+        cancellable: &gio::Cancellable,
+    ) -> Result<gdk::Texture, glib::Error> {
+        if let Some(texture) = self.lookup(path, mtime) {
+            return Ok(texture);
+        }
+
+        let (worker_path, worker_cancellable) = (path.to_path_buf(), cancellable.clone());
+        let texture = gio::spawn_blocking(move || {
+            // Don't bother if the item got rebound while we were queued
+            worker_cancellable.set_error_if_cancelled()?;
+            gdk::Texture::from_filename(&worker_path)
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(glib::Error::new(
+                gio::IOErrorEnum::Failed,
+                "Texture worker failed",
+            ))
+        })?;
+
+        self.insert(path, mtime, &texture);
+        Ok(texture)
+    }
+}
@@ -0,0 +1,625 @@
+/*
+ * Copyright 2025 The Phosh Developers
+ *
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * Author: Guido Günther <agx@sigxcpu.org>
+ */
+
+// Thumbnail generation as described in the freedesktop thumbnail spec,
+// https://specifications.freedesktop.org/thumbnail-spec/latest/
+
+use gtk::gdk_pixbuf;
+use gtk::gdk_pixbuf::prelude::*;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::config::{LOG_DOMAIN, VERSION};
+
+const THUMBNAILER_GROUP: &str = "Thumbnailer Entry";
+
+// How many thumbnailers to run at once
+const MAX_JOBS: usize = 2;
+
+// Chunk size when feeding images to gdk-pixbuf
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+    XLarge,
+    XXLarge,
+}
+
+impl ThumbnailSize {
+    // The smallest flavour that doesn't need upscaling for `size` pixels
+    pub fn for_icon_size(size: u32) -> Self {
+        [
+            ThumbnailSize::Normal,
+            ThumbnailSize::Large,
+            ThumbnailSize::XLarge,
+        ]
+        .into_iter()
+        .find(|flavour| size <= flavour.pixels())
+        .unwrap_or(ThumbnailSize::XXLarge)
+    }
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+
+    // The `thumbnail::` attribute gio uses for this flavour, e.g. `path`
+    pub fn attribute(self, name: &str) -> String {
+        let flavour = match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "xlarge",
+            ThumbnailSize::XXLarge => "xxlarge",
+        };
+        format!("thumbnail::{name}-{flavour}")
+    }
+}
+
+fn thumbnails_dir() -> PathBuf {
+    glib::user_cache_dir().join("thumbnails")
+}
+
+fn thumbnail_name(uri: &str) -> String {
+    let hash = glib::compute_checksum_for_string(glib::ChecksumType::Md5, uri).unwrap();
+    format!("{hash}.png")
+}
+
+pub fn thumbnail_path(uri: &str, size: ThumbnailSize) -> PathBuf {
+    thumbnails_dir()
+        .join(size.dir_name())
+        .join(thumbnail_name(uri))
+}
+
+fn fail_path(uri: &str) -> PathBuf {
+    thumbnails_dir()
+        .join("fail")
+        .join(format!("pfs-{VERSION}"))
+        .join(thumbnail_name(uri))
+}
+
+// Whether `path` holds a thumbnail for `uri` in its current version
+fn is_valid(path: &Path, uri: &str, mtime: u64) -> bool {
+    let Ok(pixbuf) = gdk_pixbuf::Pixbuf::from_file(path) else {
+        return false;
+    };
+
+    pixbuf.option("tEXt::Thumb::URI").as_deref() == Some(uri)
+        && pixbuf.option("tEXt::Thumb::MTime").as_deref() == Some(mtime.to_string().as_str())
+}
+
+// The spec wants the thumbnail dirs to be private
+fn create_parent_dir(path: &Path) -> Result<(), glib::Error> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|err| glib::Error::new(gio::IOErrorEnum::Failed, &err.to_string()))
+}
+
+// Store `pixbuf` as thumbnail for `uri` at `path`. The file is written under
+// a temporary name first so readers never see partial thumbnails.
+pub fn save_thumbnail(
+    pixbuf: &gdk_pixbuf::Pixbuf,
+    uri: &str,
+    mtime: u64,
+    path: &Path,
+) -> Result<(), glib::Error> {
+    create_parent_dir(path)?;
+
+    let tmp = path.with_extension(format!("png.{}", std::process::id()));
+    pixbuf.savev(
+        &tmp,
+        "png",
+        &[
+            ("tEXt::Thumb::URI", uri),
+            ("tEXt::Thumb::MTime", &mtime.to_string()),
+            ("tEXt::Software", "Phosh File Selector"),
+        ],
+    )?;
+
+    std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            glib::Error::new(gio::IOErrorEnum::Failed, &err.to_string())
+        })
+}
+
+// Remember that thumbnailing `uri` failed so we don't retry
+fn save_fail_marker(uri: &str, mtime: u64) {
+    let path = fail_path(uri);
+    let result = gdk_pixbuf::Pixbuf::new(gdk_pixbuf::Colorspace::Rgb, true, 8, 1, 1)
+        .ok_or_else(|| glib::Error::new(gio::IOErrorEnum::Failed, "Failed to allocate pixbuf"))
+        .and_then(|pixbuf| save_thumbnail(&pixbuf, uri, mtime, &path));
+
+    if let Err(err) = result {
+        glib::g_warning!(LOG_DOMAIN, "Failed to write fail marker {path:#?}: {err}");
+    }
+}
+
+// Read the `.thumbnailer` files, earlier data dirs take precedence
+fn load_thumbnailers() -> HashMap<String, String> {
+    let mut thumbnailers = HashMap::new();
+    let data_dirs = std::iter::once(glib::user_data_dir()).chain(glib::system_data_dirs());
+
+    for dir in data_dirs {
+        let Ok(entries) = std::fs::read_dir(dir.join("thumbnailers")) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("thumbnailer")) {
+                continue;
+            }
+
+            let keyfile = glib::KeyFile::new();
+            if let Err(err) = keyfile.load_from_file(&path, glib::KeyFileFlags::NONE) {
+                glib::g_debug!(LOG_DOMAIN, "Failed to load {path:#?}: {err}");
+                continue;
+            }
+
+            if let Ok(try_exec) = keyfile.string(THUMBNAILER_GROUP, "TryExec") {
+                if glib::find_program_in_path(try_exec.as_str()).is_none() {
+                    continue;
+                }
+            }
+
+            let (Ok(exec), Ok(mime_types)) = (
+                keyfile.string(THUMBNAILER_GROUP, "Exec"),
+                keyfile.string_list(THUMBNAILER_GROUP, "MimeType"),
+            ) else {
+                continue;
+            };
+
+            glib::g_debug!(LOG_DOMAIN, "Using thumbnailer {path:#?}");
+            for mime_type in mime_types.iter() {
+                thumbnailers
+                    .entry(mime_type.to_string())
+                    .or_insert_with(|| exec.to_string());
+            }
+        }
+    }
+
+    thumbnailers
+}
+
+// How a file gets thumbnailed
+#[derive(Debug)]
+enum Method {
+    // Running the external thumbnailer with this `Exec` line
+    Exec(String),
+    // Decoding the image with gdk-pixbuf
+    Builtin,
+}
+
+#[derive(Debug)]
+struct Job {
+    file: gio::File,
+    uri: String,
+    method: Method,
+    mtime: u64,
+    size: ThumbnailSize,
+}
+
+impl Job {
+    // Expand the `Exec` line's field codes
+    fn command_line(&self, exec: &str, output: &Path) -> Result<Vec<OsString>, glib::Error> {
+        let mut argv = Vec::new();
+
+        for arg in glib::shell_parse_argv(exec)? {
+            let arg = arg.to_string_lossy();
+            let mut expanded = OsString::new();
+            let mut chars = arg.chars();
+
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    expanded.push(c.encode_utf8(&mut [0; 4]));
+                    continue;
+                }
+
+                match chars.next() {
+                    Some('u') => expanded.push(&self.uri),
+                    Some('i') => {
+                        let Some(path) = self.file.path() else {
+                            return Err(glib::Error::new(
+                                gio::IOErrorEnum::NotSupported,
+                                "Thumbnailer needs a local file",
+                            ));
+                        };
+                        expanded.push(path);
+                    }
+                    Some('o') => expanded.push(output),
+                    Some('s') => expanded.push(self.size.pixels().to_string()),
+                    Some('%') => expanded.push("%"),
+                    Some(other) => {
+                        glib::g_debug!(LOG_DOMAIN, "Unknown field code %{other} in {exec:#?}");
+                    }
+                    None => expanded.push("%"),
+                }
+            }
+            argv.push(expanded);
+        }
+
+        Ok(argv)
+    }
+}
+
+type Callback = Box<dyn FnOnce(Option<PathBuf>)>;
+
+// A callback waiting for a thumbnail, dropped once `cancellable` is cancelled
+struct Waiter {
+    cancellable: gio::Cancellable,
+    callback: Callback,
+}
+
+// Why a thumbnail couldn't be created
+#[derive(Debug)]
+enum Failure {
+    // The file's content can't be thumbnailed, retrying won't help
+    Content(glib::Error),
+    // Anything else like an unreachable file or a missing helper
+    Other(glib::Error),
+}
+
+impl From<glib::Error> for Failure {
+    fn from(err: glib::Error) -> Self {
+        Failure::Other(err)
+    }
+}
+
+#[derive(Default)]
+pub struct Thumbnailer {
+    // Thumbnailer command lines by content type
+    thumbnailers: HashMap<String, String>,
+    // Content types the built-in thumbnailer handles
+    pixbuf_content_types: HashSet<String>,
+    queue: RefCell<VecDeque<Job>>,
+    running: Cell<usize>,
+    // Callbacks waiting for a thumbnail by URI and size
+    waiting: RefCell<HashMap<(String, ThumbnailSize), Vec<Waiter>>>,
+}
+
+thread_local! {
+    static THUMBNAILER: Rc<Thumbnailer> = Rc::new(Thumbnailer::new());
+}
+
+impl Thumbnailer {
+    fn new() -> Self {
+        Thumbnailer {
+            thumbnailers: load_thumbnailers(),
+            pixbuf_content_types: pixbuf_content_types(),
+            ..Default::default()
+        }
+    }
+
+    pub fn get() -> Rc<Thumbnailer> {
+        THUMBNAILER.with(Rc::clone)
+    }
+
+    // How to thumbnail `file`, `None` if it can't be thumbnailed
+    fn method(&self, file: &gio::File, content_type: &str) -> Option<Method> {
+        if let Some(exec) = self.thumbnailers.get(content_type) {
+            // Thumbnailers taking a path can't handle remote files
+            if file.path().is_some() || !exec.contains("%i") {
+                return Some(Method::Exec(exec.clone()));
+            }
+        }
+
+        // gdk-pixbuf reads remote files through GIO
+        if self.pixbuf_content_types.contains(content_type) {
+            return Some(Method::Builtin);
+        }
+
+        None
+    }
+
+    // Create a thumbnail of `size` for the file described by `info` unless there's
+    // a valid one already. `callback` gets the thumbnail's path or `None` on failure.
+    // Cancel the returned cancellable when the thumbnail isn't needed anymore.
+    // Returns `None` if the file can't be thumbnailed at all.
+    pub fn request(
+        self: &Rc<Self>,
+        info: &gio::FileInfo,
+        size: ThumbnailSize,
+        callback: impl FnOnce(Option<PathBuf>) + 'static,
+    ) -> Option<gio::Cancellable> {
+        let file = info
+            .attribute_object("standard::file")
+            .and_downcast::<gio::File>()?;
+        let content_type = info.content_type()?;
+        let method = self.method(&file, &content_type)?;
+
+        let uri = file.uri().to_string();
+        let key = (uri.clone(), size);
+        let cancellable = gio::Cancellable::new();
+        let mut waiting = self.waiting.borrow_mut();
+        let queued = waiting.contains_key(&key);
+        waiting.entry(key).or_default().push(Waiter {
+            cancellable: cancellable.clone(),
+            callback: Box::new(callback),
+        });
+        drop(waiting);
+
+        if !queued {
+            self.queue.borrow_mut().push_back(Job {
+                file,
+                uri,
+                method,
+                mtime: info.attribute_uint64("time::modified"),
+                size,
+            });
+            self.process_queue();
+        }
+
+        Some(cancellable)
+    }
+
+    // Forget about callbacks that got cancelled, `false` if none is left for `job`
+    fn is_wanted(&self, job: &Job) -> bool {
+        let key = (job.uri.clone(), job.size);
+        let mut waiting = self.waiting.borrow_mut();
+        let Some(waiters) = waiting.get_mut(&key) else {
+            return false;
+        };
+
+        waiters.retain(|waiter| !waiter.cancellable.is_cancelled());
+        if waiters.is_empty() {
+            waiting.remove(&key);
+            return false;
+        }
+
+        true
+    }
+
+    fn process_queue(self: &Rc<Self>) {
+        while self.running.get() < MAX_JOBS {
+            // Latest requests first, they're most likely still visible
+            let Some(job) = self.queue.borrow_mut().pop_back() else {
+                break;
+            };
+
+            // The item got unbound or shows another file by now
+            if !self.is_wanted(&job) {
+                glib::g_debug!(LOG_DOMAIN, "Dropping unwanted job for {:#?}", job.uri);
+                continue;
+            }
+
+            self.running.set(self.running.get() + 1);
+            let this = self.clone();
+            glib::spawn_future_local(async move {
+                let result = this.run(&job).await;
+
+                this.running.set(this.running.get() - 1);
+                this.finish(&job, result);
+                this.process_queue();
+            });
+        }
+    }
+
+    async fn run(&self, job: &Job) -> Result<PathBuf, glib::Error> {
+        let output = thumbnail_path(&job.uri, job.size);
+        let fail = fail_path(&job.uri);
+
+        // Another process might have created it meanwhile, or failed before
+        let (path, uri, mtime) = (output.clone(), job.uri.clone(), job.mtime);
+        let (exists, failed) = gio::spawn_blocking(move || {
+            (
+                is_valid(&path, &uri, mtime),
+                is_valid(&fail_path(&uri), &uri, mtime),
+            )
+        })
+        .await
+        .unwrap_or((false, false));
+
+        if exists {
+            return Ok(output);
+        }
+        if failed {
+            return Err(glib::Error::new(
+                gio::IOErrorEnum::Failed,
+                &format!("Thumbnailing failed before, see {fail:#?}"),
+            ));
+        }
+
+        let result = match &job.method {
+            Method::Exec(exec) => self.run_thumbnailer(job, exec, &output).await,
+            Method::Builtin => self.run_builtin(job, &output).await,
+        };
+
+        match result {
+            Ok(()) => Ok(output),
+            // Only remember failures that would happen again
+            Err(Failure::Content(err)) => {
+                let (uri, mtime) = (job.uri.clone(), job.mtime);
+                let _ = gio::spawn_blocking(move || save_fail_marker(&uri, mtime)).await;
+                Err(err)
+            }
+            Err(Failure::Other(err)) => {
+                glib::g_warning!(LOG_DOMAIN, "Failed to thumbnail {:#?}: {err}", job.uri);
+                Err(err)
+            }
+        }
+    }
+
+    async fn run_thumbnailer(&self, job: &Job, exec: &str, output: &Path) -> Result<(), Failure> {
+        // The thumbnailer's output lacks the metadata the spec requires, so
+        // let it write elsewhere and add that afterwards. Next to the final
+        // file as the cache dir is private, unlike /tmp.
+        let tmp = output.with_extension(format!(
+            "{}-{:08x}.png",
+            std::process::id(),
+            glib::random_int()
+        ));
+        create_parent_dir(&tmp)?;
+        let argv = job.command_line(exec, &tmp)?;
+
+        glib::g_debug!(LOG_DOMAIN, "Thumbnailing {:#?} with {argv:#?}", job.uri);
+        let argv = argv.iter().map(|arg| arg.as_os_str()).collect::<Vec<_>>();
+        let subprocess = gio::Subprocess::newv(
+            &argv,
+            gio::SubprocessFlags::STDOUT_SILENCE | gio::SubprocessFlags::STDERR_SILENCE,
+        )?;
+        if let Err(err) = subprocess.wait_check_future().await {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(Failure::Content(err));
+        }
+
+        let (uri, mtime, pixels) = (job.uri.clone(), job.mtime, job.size.pixels() as i32);
+        let output = output.to_path_buf();
+        gio::spawn_blocking(move || {
+            let result = gdk_pixbuf::Pixbuf::from_file(&tmp)
+                .map_err(Failure::Content)
+                .and_then(|pixbuf| {
+                    // Not all thumbnailers honor the requested size
+                    let pixbuf = scale_to_fit(pixbuf, pixels);
+                    save_thumbnail(&pixbuf, &uri, mtime, &output).map_err(Failure::Other)
+                });
+            let _ = std::fs::remove_file(&tmp);
+            result
+        })
+        .await
+        .unwrap_or_else(worker_failed)
+    }
+
+    // Without an external thumbnailer we can still decode images ourselves
+    async fn run_builtin(&self, job: &Job, output: &Path) -> Result<(), Failure> {
+        glib::g_debug!(LOG_DOMAIN, "Thumbnailing {:#?} with gdk-pixbuf", job.uri);
+
+        let (uri, mtime, pixels) = (job.uri.clone(), job.mtime, job.size.pixels() as i32);
+        let output = output.to_path_buf();
+        gio::spawn_blocking(move || {
+            let pixbuf = load_scaled(&uri, pixels)?;
+            save_thumbnail(&pixbuf, &uri, mtime, &output).map_err(Failure::Other)
+        })
+        .await
+        .unwrap_or_else(worker_failed)
+    }
+
+    fn finish(&self, job: &Job, result: Result<PathBuf, glib::Error>) {
+        let path = match result {
+            Ok(path) => Some(path),
+            Err(err) => {
+                glib::g_debug!(LOG_DOMAIN, "No thumbnail for {:#?}: {err}", job.uri);
+                None
+            }
+        };
+
+        let waiters = self
+            .waiting
+            .borrow_mut()
+            .remove(&(job.uri.clone(), job.size))
+            .unwrap_or_default();
+        for waiter in waiters {
+            if !waiter.cancellable.is_cancelled() {
+                (waiter.callback)(path.clone());
+            }
+        }
+    }
+}
+
+// The size to shrink `width` x `height` to so the larger side is at most
+// `pixels` long, `None` if it fits already
+fn fit_size(width: i32, height: i32, pixels: i32) -> Option<(i32, i32)> {
+    if width <= pixels && height <= pixels {
+        return None;
+    }
+
+    let scale = pixels as f64 / width.max(height) as f64;
+    Some((
+        ((width as f64 * scale).round() as i32).max(1),
+        ((height as f64 * scale).round() as i32).max(1),
+    ))
+}
+
+// Shrink `pixbuf` so its larger side is at most `pixels` long
+pub fn scale_to_fit(pixbuf: gdk_pixbuf::Pixbuf, pixels: i32) -> gdk_pixbuf::Pixbuf {
+    let Some((width, height)) = fit_size(pixbuf.width(), pixbuf.height(), pixels) else {
+        return pixbuf;
+    };
+
+    pixbuf
+        .scale_simple(width, height, gdk_pixbuf::InterpType::Bilinear)
+        .unwrap_or(pixbuf)
+}
+
+// Decode the image at `uri` right at thumbnail size as photos are way
+// larger than thumbnails. Blocks, so use from a worker thread.
+fn load_scaled(uri: &str, pixels: i32) -> Result<gdk_pixbuf::Pixbuf, Failure> {
+    let stream = gio::File::for_uri(uri).read(None::<&gio::Cancellable>)?;
+    let loader = gdk_pixbuf::PixbufLoader::new();
+    loader.connect_size_prepared(move |loader, width, height| {
+        if let Some((width, height)) = fit_size(width, height, pixels) {
+            loader.set_size(width, height);
+        }
+    });
+
+    // Read errors are about the file being reachable, decoding errors
+    // about its content
+    let result: Result<(), Failure> = (|| loop {
+        let bytes = stream.read_bytes(READ_CHUNK_SIZE, None::<&gio::Cancellable>)?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        loader.write_bytes(&bytes).map_err(Failure::Content)?;
+    })();
+    // Always close the loader, it complains otherwise
+    let closed = loader.close().map_err(Failure::Content);
+    result.and(closed)?;
+
+    let pixbuf = loader.pixbuf().ok_or_else(|| {
+        Failure::Content(glib::Error::new(
+            gdk_pixbuf::PixbufError::Failed,
+            "Image didn't decode",
+        ))
+    })?;
+    // Photos are often stored rotated
+    Ok(pixbuf.apply_embedded_orientation().unwrap_or(pixbuf))
+}
+
+// The content types gdk-pixbuf can load
+fn pixbuf_content_types() -> HashSet<String> {
+    gdk_pixbuf::Pixbuf::formats()
+        .iter()
+        .filter(|format| !format.is_disabled())
+        .flat_map(|format| format.mime_types())
+        .map(|mime_type| mime_type.to_string())
+        .collect()
+}
+
+fn worker_failed<T>(_: Box<dyn std::any::Any + Send>) -> Result<T, Failure> {
+    Err(Failure::Other(glib::Error::new(
+        gio::IOErrorEnum::Failed,
+        "Thumbnail worker failed",
+    )))
+}
@@ -41,18 +41,32 @@ pub fn folder_to_name(file: gio::File) -> String {
     };
 
     let name = match file.path() {
-        Some(path) => path
-            .file_name()
-            .unwrap_or_default()
-            .to_str()
-            .unwrap_or_default()
-            .to_string(),
-        None => uri.split(":").next().unwrap_or_default().to_string(),
+        Some(path) => match path.file_name() {
+            Some(name) => glib::filename_display_name(name).to_string(),
+            // The root folder
+            None => glib::filename_display_name(&path).to_string(),
+        },
+        None => match file.parent() {
+            Some(_) => file_display_name(&file),
+            // Root of a location like sftp://host/, prefer the host
+            None => glib::Uri::parse(&uri, glib::UriFlags::NONE)
+                .ok()
+                .and_then(|uri| uri.host())
+                .map(|host| host.to_string())
+                .unwrap_or_else(|| uri.split(":").next().unwrap_or_default().to_string()),
+        },
     };
 
     name
 }
 
+// The file's base name for display, also when it isn't valid UTF-8
+pub fn file_display_name(file: &gio::File) -> String {
+    file.basename()
+        .map(|name| glib::filename_display_name(name).to_string())
+        .unwrap_or_default()
+}
+
 pub fn folder_to_icon_name(file: gio::File) -> &'static str {
     let uri = file.uri();
     match uri.as_str() {
@@ -88,3 +102,177 @@ pub fn is_valid_folder(folder: &Option<gio::File>) -> bool {
 
     folder.as_ref().unwrap().path().is_some()
 }
+
+// Maximum length of a file name in bytes (NAME_MAX on Linux)
+pub const NAME_MAX: usize = 255;
+
+// File systems that can't store some characters valid on Unix
+static FAT_FILESYSTEMS: [&str; 4] = ["msdos", "vfat", "fat", "exfat"];
+static FAT_RESERVED_CHARS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+// Device names these file systems reserve, also with an extension
+static FAT_RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+static FAT_RESERVED_PORTS: [&str; 2] = ["COM", "LPT"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FilenameError {
+    Empty,
+    Reserved,
+    Separator,
+    Nul,
+    TooLong,
+}
+
+impl FilenameError {
+    pub fn message(&self) -> String {
+        match self {
+            FilenameError::Empty => gettextrs::gettext("The name can't be empty"),
+            FilenameError::Reserved => gettextrs::gettext("“.” and “..” are reserved names"),
+            FilenameError::Separator => gettextrs::gettext("The name can't contain “/”"),
+            FilenameError::Nul => gettextrs::gettext("The name contains invalid characters"),
+            FilenameError::TooLong => gettextrs::gettext("The name is too long"),
+        }
+    }
+}
+
+// Check if `name` can be used as a single path component
+pub fn validate_filename(name: &str) -> Result<(), FilenameError> {
+    if name.is_empty() {
+        return Err(FilenameError::Empty);
+    }
+
+    if name == "." || name == ".." {
+        return Err(FilenameError::Reserved);
+    }
+
+    if name.contains('/') {
+        return Err(FilenameError::Separator);
+    }
+
+    if name.contains('\0') {
+        return Err(FilenameError::Nul);
+    }
+
+    if name.len() > NAME_MAX {
+        return Err(FilenameError::TooLong);
+    }
+
+    Ok(())
+}
+
+// Warn about valid names the file system of type `fs_type` can't store
+pub fn filename_warning(name: &str, fs_type: Option<&str>) -> Option<String> {
+    let fs_type = fs_type?;
+
+    if !FAT_FILESYSTEMS.contains(&fs_type) {
+        return None;
+    }
+
+    if name
+        .chars()
+        .any(|c| c.is_control() || FAT_RESERVED_CHARS.contains(&c))
+        || name.ends_with('.')
+        || name.ends_with(' ')
+    {
+        return Some(gettextrs::gettext(
+            "This name contains characters the file system can't store",
+        ));
+    }
+
+    if is_fat_reserved_name(name) {
+        return Some(gettextrs::gettext(
+            "This name is reserved on the file system",
+        ));
+    }
+
+    None
+}
+
+fn is_fat_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().to_uppercase();
+
+    if FAT_RESERVED_NAMES.contains(&stem.as_str()) {
+        return true;
+    }
+
+    FAT_RESERVED_PORTS.iter().any(|port| {
+        stem.strip_prefix(port)
+            .is_some_and(|n| n.len() == 1 && matches!(n.as_bytes()[0], b'1'..=b'9'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_filenames() {
+        for name in ["a", "...", ".hidden", "a b", "ünïcödé", "x..", "-"] {
+            assert_eq!(validate_filename(name), Ok(()), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_filenames() {
+        assert_eq!(validate_filename(""), Err(FilenameError::Empty));
+        assert_eq!(validate_filename("."), Err(FilenameError::Reserved));
+        assert_eq!(validate_filename(".."), Err(FilenameError::Reserved));
+        assert_eq!(validate_filename("a/b"), Err(FilenameError::Separator));
+        assert_eq!(validate_filename("../x"), Err(FilenameError::Separator));
+        assert_eq!(validate_filename("/"), Err(FilenameError::Separator));
+        assert_eq!(validate_filename("a\0b"), Err(FilenameError::Nul));
+    }
+
+    #[test]
+    fn filename_length() {
+        assert_eq!(validate_filename(&"a".repeat(NAME_MAX)), Ok(()));
+        assert_eq!(
+            validate_filename(&"a".repeat(NAME_MAX + 1)),
+            Err(FilenameError::TooLong)
+        );
+
+        // The limit is in bytes, not characters
+        assert_eq!(validate_filename(&"ä".repeat(127)), Ok(()));
+        assert_eq!(
+            validate_filename(&"ä".repeat(128)),
+            Err(FilenameError::TooLong)
+        );
+        assert_eq!(validate_filename(&"€".repeat(85)), Ok(()));
+        assert_eq!(
+            validate_filename(&format!("{}a", "€".repeat(85))),
+            Err(FilenameError::TooLong)
+        );
+    }
+
+    #[test]
+    fn fat_warnings() {
+        let fat = Some("vfat");
+
+        for name in [
+            "a:b",
+            "a*",
+            "what?",
+            "\"quoted\"",
+            "<tag>",
+            "a|b",
+            "back\\slash",
+            "dot.",
+            "space ",
+            "CON",
+            "con.txt",
+            "Nul",
+            "aux.tar.gz",
+            "COM1",
+            "lpt9.log",
+        ] {
+            assert!(filename_warning(name, fat).is_some(), "{name:?}");
+        }
+
+        for name in ["a", "a.txt", "CONSOLE", "com0", "COM10", "lpt", ".hidden"] {
+            assert_eq!(filename_warning(name, fat), None, "{name:?}");
+        }
+
+        // Only FAT like file systems are affected
+        assert_eq!(filename_warning("CON", Some("ext4")), None);
+        assert_eq!(filename_warning("a:b", None), None);
+    }
+}
@@ -0,0 +1,123 @@
+#!/bin/bash
+#
+# Copyright (C) 2025 The Phosh developers
+#
+# SPDX-License-Identifier: GPL-3.0-or-later
+#
+# Run pfs-portal on a private session bus and check that file chooser
+# requests get exported and can be closed by the frontend.
+#
+# Usage: portal-test.sh <run-script> <pfs-portal>
+
+set -e
+
+RUN="$1"
+PORTAL="$2"
+
+BUS_NAME=org.freedesktop.impl.portal.desktop.pfs
+OBJECT_PATH=/org/freedesktop/portal/desktop
+REQUEST_PATH=/org/freedesktop/portal/desktop/request/1_1
+
+if [ -z "${RUN}" ] || [ -z "${PORTAL}" ]; then
+  echo "Usage: $0 <run-script> <pfs-portal>" >&2
+  exit 1
+fi
+
+if [ -z "${PFS_PORTAL_TEST_BUS}" ]; then
+  # Make sure we never talk to the user's session bus
+  PFS_PORTAL_TEST_BUS=1 exec dbus-run-session -- "$0" "$@"
+fi
+
+if [ -z "${WAYLAND_DISPLAY}" ] && [ -z "${DISPLAY}" ]; then
+  echo "No display, skipping"
+  exit 77
+fi
+
+WORKDIR=$(mktemp -d)
+PIDS=()
+
+cleanup() {
+  for pid in "${PIDS[@]}"; do
+    kill "${pid}" 2>/dev/null || true
+  done
+  rm -rf "${WORKDIR}"
+}
+trap cleanup EXIT
+
+wait_for() {
+  local tries=50
+
+  while ! "$@" >/dev/null 2>&1; do
+    tries=$((tries - 1))
+    if [ "${tries}" -le 0 ]; then
+      return 1
+    fi
+    sleep 0.1
+  done
+}
+
+has_bus_name() {
+  gdbus call --session --dest org.freedesktop.DBus \
+    --object-path /org/freedesktop/DBus \
+    --method org.freedesktop.DBus.NameHasOwner "${BUS_NAME}" | grep -q true
+}
+
+has_request() {
+  gdbus introspect --session --dest "${BUS_NAME}" --object-path "$1" |
+    grep -q org.freedesktop.impl.portal.Request
+}
+
+# Issue a file chooser request, check it's exported and close it again
+check_request() {
+  local method="$1"
+  local handle="${REQUEST_PATH}/$2"
+  local title="$3"
+  local options="$4"
+  local out="${WORKDIR}/$2.out"
+
+  echo "Checking ${method}"
+
+  # The call only returns once the request got answered
+  gdbus call --session --dest "${BUS_NAME}" --object-path "${OBJECT_PATH}" \
+    --method "org.freedesktop.impl.portal.FileChooser.${method}" \
+    "${handle}" "org.example.Test" "" "${title}" "${options}" >"${out}" &
+  local call=$!
+  PIDS+=("${call}")
+
+  if ! wait_for has_request "${handle}"; then
+    echo "Request ${handle} not exported" >&2
+    return 1
+  fi
+
+  gdbus call --session --dest "${BUS_NAME}" --object-path "${handle}" \
+    --method org.freedesktop.impl.portal.Request.Close >/dev/null
+
+  if ! wait "${call}"; then
+    echo "${method} failed" >&2
+    return 1
+  fi
+
+  # Closed requests get cancelled
+  if ! grep -q '^(uint32 1,' "${out}"; then
+    echo "Unexpected response to ${method}: $(cat "${out}")" >&2
+    return 1
+  fi
+
+  if has_request "${handle}"; then
+    echo "Request ${handle} still exported after Close" >&2
+    return 1
+  fi
+}
+
+G_MESSAGES_DEBUG=pfs "${RUN}" "${PORTAL}" &
+PIDS+=($!)
+
+if ! wait_for has_bus_name; then
+  echo "${BUS_NAME} did not show up on the bus" >&2
+  exit 1
+fi
+
+check_request OpenFile open "Open a File" '{"multiple": <true>}'
+check_request SaveFile save "Save a File" '{"current_name": <"test.txt">}'
+
+echo "All good"